@@ -0,0 +1,1442 @@
+//! Off-chain instruction builders for `pnft_transfer`.
+//!
+//! Every builder only needs the mint and the wallets involved, all the metadata, edition,
+//! token record, listing and ATA addresses are derived here. The ruleset (if the item has one)
+//! is read from the item's metadata, either by passing it in or by fetching it with
+//! [`AccountFetcher`].
+use anchor_lang::{
+    solana_program::{instruction::Instruction, pubkey::Pubkey, system_program, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token;
+use mpl_token_metadata::state::{Metadata, ProgrammableConfig, TokenMetadataAccount};
+use pnft_transfer::{pda, AuthorizationDataLocal, CollectionMultiplier, CollectionState};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("metadata account {0} not found")]
+    MetadataNotFound(Pubkey),
+    #[error("metadata account {0} couldn't be decoded")]
+    BadMetadata(Pubkey),
+}
+
+/// Anything that can return an account's data, e.g. `|key| rpc.get_account_data(key).ok()`.
+pub trait AccountFetcher {
+    fn account_data(&self, address: &Pubkey) -> Option<Vec<u8>>;
+}
+
+impl<F: Fn(&Pubkey) -> Option<Vec<u8>>> AccountFetcher for F {
+    fn account_data(&self, address: &Pubkey) -> Option<Vec<u8>> {
+        self(address)
+    }
+}
+
+/// The ruleset from a decoded metadata account, if it has one.
+pub fn rule_set(metadata: &Metadata) -> Option<Pubkey> {
+    match metadata.programmable_config {
+        Some(ProgrammableConfig::V1 { rule_set }) => rule_set,
+        None => None,
+    }
+}
+
+/// The verified collection from a decoded metadata account, if it has one.
+pub fn verified_collection(metadata: &Metadata) -> Option<Pubkey> {
+    metadata
+        .collection
+        .as_ref()
+        .filter(|collection| collection.verified)
+        .map(|collection| collection.key)
+}
+
+/// Fetches and decodes the item's metadata to find its ruleset.
+pub fn fetch_rule_set(
+    fetcher: &impl AccountFetcher,
+    mint: &Pubkey,
+) -> Result<Option<Pubkey>, ClientError> {
+    let address = find_metadata(mint);
+    let data = fetcher
+        .account_data(&address)
+        .ok_or(ClientError::MetadataNotFound(address))?;
+    let metadata =
+        Metadata::safe_deserialize(&data).map_err(|_| ClientError::BadMetadata(address))?;
+    Ok(rule_set(&metadata))
+}
+
+fn find_metadata(mint: &Pubkey) -> Pubkey {
+    pda::find_metadata(mint).0
+}
+
+fn find_edition(mint: &Pubkey) -> Pubkey {
+    pda::find_edition(mint).0
+}
+
+fn find_token_record(mint: &Pubkey, token: &Pubkey) -> Pubkey {
+    pda::find_token_record(mint, token).0
+}
+
+fn find_listing(mint: &Pubkey, seller: &Pubkey, nonce: u64) -> Pubkey {
+    pda::find_listing(mint, seller, nonce).0
+}
+
+fn find_stake_record(mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+    pda::find_stake_record(mint, owner).0
+}
+
+fn find_loan(lender: &Pubkey, nonce: u64) -> Pubkey {
+    pda::find_loan(lender, nonce).0
+}
+
+fn find_rental(mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+    pda::find_rental(mint, owner).0
+}
+
+fn find_bundle(seller: &Pubkey, nonce: u64) -> Pubkey {
+    pda::find_bundle(seller, nonce).0
+}
+
+fn find_vault(owner: &Pubkey) -> Pubkey {
+    pda::find_vault(owner).0
+}
+
+fn find_multisig(creator: &Pubkey, nonce: u64) -> Pubkey {
+    pda::find_multisig(creator, nonce).0
+}
+
+// unverified items go by the default pubkey's status
+fn find_collection_status(collection: Option<Pubkey>) -> Pubkey {
+    pda::find_collection_status(&collection.unwrap_or_default()).0
+}
+
+// the ruleset goes in remaining accounts, and the ix is told whether it's there
+fn rule_set_metas(rule_set: Option<Pubkey>) -> Vec<anchor_lang::prelude::AccountMeta> {
+    rule_set
+        .map(|rule_set| vec![anchor_lang::prelude::AccountMeta::new_readonly(rule_set, false)])
+        .unwrap_or_default()
+}
+
+// one item's slice of a bundle ix's remaining accounts, moving it from `from`'s ATA to `to`'s.
+// Items without a ruleset get the auth rules program in the ruleset slot
+fn bundle_item_metas(
+    mint: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Vec<anchor_lang::prelude::AccountMeta> {
+    use anchor_lang::prelude::AccountMeta;
+    let from_ata = get_associated_token_address(from, mint);
+    let to_ata = get_associated_token_address(to, mint);
+    vec![
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(find_metadata(mint), false),
+        AccountMeta::new_readonly(find_edition(mint), false),
+        AccountMeta::new(from_ata, false),
+        AccountMeta::new(to_ata, false),
+        AccountMeta::new(find_token_record(mint, &from_ata), false),
+        AccountMeta::new(find_token_record(mint, &to_ata), false),
+        AccountMeta::new_readonly(rule_set.unwrap_or(mpl_token_auth_rules::ID), false),
+    ]
+}
+
+// every item's slice, then (list / buy only) every item's collection status
+fn bundle_metas(
+    items: &[BundleItem],
+    from: &Pubkey,
+    to: &Pubkey,
+    collection_statuses: bool,
+) -> Vec<anchor_lang::prelude::AccountMeta> {
+    let mut metas: Vec<_> = items
+        .iter()
+        .flat_map(|item| bundle_item_metas(&item.mint, from, to, item.rule_set))
+        .collect();
+    if collection_statuses {
+        metas.extend(items.iter().map(|item| {
+            anchor_lang::prelude::AccountMeta::new_readonly(
+                find_collection_status(item.collection),
+                false,
+            )
+        }));
+    }
+    metas
+}
+
+fn instruction(
+    mut accounts: Vec<anchor_lang::prelude::AccountMeta>,
+    remaining: Vec<anchor_lang::prelude::AccountMeta>,
+    data: impl InstructionData,
+) -> Instruction {
+    accounts.extend(remaining);
+    Instruction {
+        program_id: pnft_transfer::ID,
+        accounts,
+        data: data.data(),
+    }
+}
+
+/// One item of a bundle: its verified collection (for the marketplace controls) and ruleset,
+/// `None` when it has neither.
+#[derive(Clone, Copy, Debug)]
+pub struct BundleItem {
+    pub mint: Pubkey,
+    pub collection: Option<Pubkey>,
+    pub rule_set: Option<Pubkey>,
+}
+
+/// `transfer_pnft` from `owner`'s ATA to `receiver`'s ATA, the receiver's ATA has to exist.
+#[derive(Clone, Debug)]
+pub struct TransferPnft {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    /// Pays the token record rent instead of the owner.
+    pub payer: Option<Pubkey>,
+    pub receiver: Pubkey,
+    pub rule_set: Option<Pubkey>,
+    pub authorization_data: Option<AuthorizationDataLocal>,
+    pub memo: Option<String>,
+}
+
+impl TransferPnft {
+    pub fn new(mint: Pubkey, owner: Pubkey, receiver: Pubkey) -> Self {
+        Self {
+            mint,
+            owner,
+            payer: None,
+            receiver,
+            rule_set: None,
+            authorization_data: None,
+            memo: None,
+        }
+    }
+
+    /// Has someone else (e.g. a relayer) cover the rent, they have to sign as well.
+    pub fn payer(mut self, payer: Pubkey) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    pub fn rule_set(mut self, rule_set: Option<Pubkey>) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn fetch_rule_set(self, fetcher: &impl AccountFetcher) -> Result<Self, ClientError> {
+        let rule_set = fetch_rule_set(fetcher, &self.mint)?;
+        Ok(self.rule_set(rule_set))
+    }
+
+    pub fn authorization_data(mut self, authorization_data: AuthorizationDataLocal) -> Self {
+        self.authorization_data = Some(authorization_data);
+        self
+    }
+
+    /// Reference for matching the transfer to an off-chain order, it's emitted in the
+    /// `Transferred` event and also written through the SPL Memo program.
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let src = get_associated_token_address(&self.owner, &self.mint);
+        let dest = get_associated_token_address(&self.receiver, &self.mint);
+        let accounts = pnft_transfer::accounts::TransferPNFT {
+            owner: self.owner,
+            receiver: self.receiver,
+            src,
+            dest,
+            nft_mint: self.mint,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            associated_token_program: associated_token::ID,
+            nft_metadata: find_metadata(&self.mint),
+            edition: find_edition(&self.mint),
+            owner_token_record: find_token_record(&self.mint, &src),
+            dest_token_record: find_token_record(&self.mint, &dest),
+            pnft_shared: pnft_transfer::accounts::ProgNftShared {
+                token_metadata_program: mpl_token_metadata::ID,
+                instructions: sysvar::instructions::ID,
+                authorization_rules_program: mpl_token_auth_rules::ID,
+            },
+        };
+        let mut remaining = rule_set_metas(self.rule_set);
+        if self.memo.is_some() {
+            remaining.push(anchor_lang::prelude::AccountMeta::new_readonly(spl_memo::id(), false));
+        }
+        // the payer slot is last, after the ruleset and memo program
+        if let Some(payer) = self.payer.filter(|payer| *payer != self.owner) {
+            remaining.push(anchor_lang::prelude::AccountMeta::new(payer, true));
+        }
+        instruction(
+            accounts.to_account_metas(None),
+            remaining,
+            pnft_transfer::instruction::TransferPnft {
+                authorization_data: self.authorization_data.clone(),
+                rules_acc_present: self.rule_set.is_some(),
+                memo: self.memo.clone(),
+            },
+        )
+    }
+}
+
+/// `list_pnft`, escrows `seller`'s pNFT under the (mint, seller, nonce) listing. Only 1/1
+/// pNFTs can be listed.
+#[derive(Clone, Debug)]
+pub struct ListPnft {
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    pub price: u64,
+    pub rule_set: Option<Pubkey>,
+    pub collection: Option<Pubkey>,
+    pub authorization_data: Option<AuthorizationDataLocal>,
+}
+
+impl ListPnft {
+    pub fn new(mint: Pubkey, seller: Pubkey, price: u64) -> Self {
+        Self {
+            mint,
+            seller,
+            nonce: 0,
+            price,
+            rule_set: None,
+            collection: None,
+            authorization_data: None,
+        }
+    }
+
+    pub fn nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn rule_set(mut self, rule_set: Option<Pubkey>) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn fetch_rule_set(self, fetcher: &impl AccountFetcher) -> Result<Self, ClientError> {
+        let rule_set = fetch_rule_set(fetcher, &self.mint)?;
+        Ok(self.rule_set(rule_set))
+    }
+
+    /// The item's verified collection, its status is checked before listing.
+    pub fn collection(mut self, collection: Option<Pubkey>) -> Self {
+        self.collection = collection;
+        self
+    }
+
+    pub fn authorization_data(mut self, authorization_data: AuthorizationDataLocal) -> Self {
+        self.authorization_data = Some(authorization_data);
+        self
+    }
+
+    pub fn listing(&self) -> Pubkey {
+        find_listing(&self.mint, &self.seller, self.nonce)
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let listing = self.listing();
+        let authority_item_token = get_associated_token_address(&self.seller, &self.mint);
+        let listing_item_token = get_associated_token_address(&listing, &self.mint);
+        let accounts = pnft_transfer::accounts::ListPNFT {
+            item: self.mint,
+            authority_item_token,
+            listing,
+            listing_item_token,
+            authority: self.seller,
+            config: pda::find_config().0,
+            collection_status: find_collection_status(self.collection),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            associated_token_program: associated_token::ID,
+            item_metadata: find_metadata(&self.mint),
+            edition: find_edition(&self.mint),
+            authority_token_record: find_token_record(&self.mint, &authority_item_token),
+            listing_token_record: find_token_record(&self.mint, &listing_item_token),
+            token_metadata_program: mpl_token_metadata::ID,
+            instructions: sysvar::instructions::ID,
+            authorization_rules_program: mpl_token_auth_rules::ID,
+        };
+        instruction(
+            accounts.to_account_metas(None),
+            rule_set_metas(self.rule_set),
+            pnft_transfer::instruction::ListPnft {
+                price: self.price,
+                nonce: self.nonce,
+                authorization_data: self.authorization_data.clone(),
+                rules_acc_present: self.rule_set.is_some(),
+            },
+        )
+    }
+}
+
+/// `buy_pnft`, `buyer`'s ATA has to exist. Fails if the listing's price, seller or nonce
+/// changed from what's passed here.
+#[derive(Clone, Debug)]
+pub struct BuyPnft {
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    pub buyer: Pubkey,
+    // as shown to the buyer
+    pub expected_price: u64,
+    pub rule_set: Option<Pubkey>,
+    pub collection: Option<Pubkey>,
+}
+
+impl BuyPnft {
+    pub fn new(mint: Pubkey, seller: Pubkey, buyer: Pubkey, expected_price: u64) -> Self {
+        Self {
+            mint,
+            seller,
+            nonce: 0,
+            buyer,
+            expected_price,
+            rule_set: None,
+            collection: None,
+        }
+    }
+
+    pub fn nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn rule_set(mut self, rule_set: Option<Pubkey>) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn fetch_rule_set(self, fetcher: &impl AccountFetcher) -> Result<Self, ClientError> {
+        let rule_set = fetch_rule_set(fetcher, &self.mint)?;
+        Ok(self.rule_set(rule_set))
+    }
+
+    /// The listing's collection, its status is checked before buying.
+    pub fn collection(mut self, collection: Option<Pubkey>) -> Self {
+        self.collection = collection;
+        self
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let listing = find_listing(&self.mint, &self.seller, self.nonce);
+        let listing_item_token = get_associated_token_address(&listing, &self.mint);
+        let buyer_item_token = get_associated_token_address(&self.buyer, &self.mint);
+        let accounts = pnft_transfer::accounts::BuyPNFT {
+            item: self.mint,
+            buyer_item_token,
+            listing,
+            seller: self.seller,
+            listing_item_token,
+            buyer: self.buyer,
+            config: pda::find_config().0,
+            collection_status: find_collection_status(self.collection),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            associated_token_program: associated_token::ID,
+            item_metadata: find_metadata(&self.mint),
+            edition: find_edition(&self.mint),
+            buyer_token_record: find_token_record(&self.mint, &buyer_item_token),
+            listing_token_record: find_token_record(&self.mint, &listing_item_token),
+            token_metadata_program: mpl_token_metadata::ID,
+            instructions: sysvar::instructions::ID,
+            authorization_rules_program: mpl_token_auth_rules::ID,
+            // the account is only read when the item has a ruleset, anything goes otherwise
+            ruleset: self.rule_set.unwrap_or(mpl_token_metadata::ID),
+        };
+        instruction(
+            accounts.to_account_metas(None),
+            vec![],
+            pnft_transfer::instruction::BuyPnft {
+                expected_price: self.expected_price,
+                expected_seller: self.seller,
+                expected_nonce: Some(self.nonce),
+            },
+        )
+    }
+}
+
+/// `stake_pnft`, locks the item in `owner`'s ATA with the stake record as its Staking delegate.
+#[derive(Clone, Debug)]
+pub struct StakePnft {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub rule_set: Option<Pubkey>,
+    pub authorization_data: Option<AuthorizationDataLocal>,
+}
+
+impl StakePnft {
+    pub fn new(mint: Pubkey, owner: Pubkey) -> Self {
+        Self {
+            mint,
+            owner,
+            rule_set: None,
+            authorization_data: None,
+        }
+    }
+
+    pub fn rule_set(mut self, rule_set: Option<Pubkey>) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn fetch_rule_set(self, fetcher: &impl AccountFetcher) -> Result<Self, ClientError> {
+        let rule_set = fetch_rule_set(fetcher, &self.mint)?;
+        Ok(self.rule_set(rule_set))
+    }
+
+    pub fn authorization_data(mut self, authorization_data: AuthorizationDataLocal) -> Self {
+        self.authorization_data = Some(authorization_data);
+        self
+    }
+
+    pub fn stake_record(&self) -> Pubkey {
+        find_stake_record(&self.mint, &self.owner)
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let item_token = get_associated_token_address(&self.owner, &self.mint);
+        let accounts = pnft_transfer::accounts::StakePNFT {
+            item: self.mint,
+            item_token,
+            stake_record: self.stake_record(),
+            owner: self.owner,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            item_metadata: find_metadata(&self.mint),
+            edition: find_edition(&self.mint),
+            item_token_record: find_token_record(&self.mint, &item_token),
+            token_metadata_program: mpl_token_metadata::ID,
+            instructions: sysvar::instructions::ID,
+            authorization_rules_program: mpl_token_auth_rules::ID,
+        };
+        instruction(
+            accounts.to_account_metas(None),
+            rule_set_metas(self.rule_set),
+            pnft_transfer::instruction::StakePnft {
+                authorization_data: self.authorization_data.clone(),
+                rules_acc_present: self.rule_set.is_some(),
+            },
+        )
+    }
+}
+
+/// `unstake_pnft`, unlocks the item and closes the stake record to `owner`.
+pub fn unstake_pnft(mint: &Pubkey, owner: &Pubkey, rule_set: Option<Pubkey>) -> Instruction {
+    let item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::UnstakePNFT {
+        item: *mint,
+        item_token,
+        stake_record: find_stake_record(mint, owner),
+        owner: *owner,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        item_token_record: find_token_record(mint, &item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::UnstakePnft {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `init_reward_pool` for `reward_mint`, signed by and paid for by `authority`. Only items in
+/// one of the `multipliers` collections can claim from it.
+pub fn init_reward_pool(
+    authority: &Pubkey,
+    reward_mint: &Pubkey,
+    rate_per_second: u64,
+    multipliers: Vec<CollectionMultiplier>,
+) -> Instruction {
+    let reward_pool = pda::find_reward_pool(authority, reward_mint).0;
+    let accounts = pnft_transfer::accounts::InitRewardPool {
+        reward_pool,
+        reward_mint: *reward_mint,
+        vault: get_associated_token_address(&reward_pool, reward_mint),
+        authority: *authority,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::InitRewardPool {
+            rate_per_second,
+            multipliers,
+        },
+    )
+}
+
+/// `update_reward_pool`, replaces the rate and multipliers.
+pub fn update_reward_pool(
+    authority: &Pubkey,
+    reward_mint: &Pubkey,
+    rate_per_second: u64,
+    multipliers: Vec<CollectionMultiplier>,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::UpdateRewardPool {
+        reward_pool: pda::find_reward_pool(authority, reward_mint).0,
+        authority: *authority,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::UpdateRewardPool {
+            rate_per_second,
+            multipliers,
+        },
+    )
+}
+
+/// `claim_rewards` for `owner`'s staked `mint` from the (pool authority, reward mint) pool.
+pub fn claim_rewards(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    pool_authority: &Pubkey,
+    reward_mint: &Pubkey,
+) -> Instruction {
+    let reward_pool = pda::find_reward_pool(pool_authority, reward_mint).0;
+    let accounts = pnft_transfer::accounts::ClaimRewards {
+        item: *mint,
+        stake_record: find_stake_record(mint, owner),
+        reward_pool,
+        reward_mint: *reward_mint,
+        vault: get_associated_token_address(&reward_pool, reward_mint),
+        owner_reward_token: get_associated_token_address(owner, reward_mint),
+        owner: *owner,
+        item_metadata: find_metadata(mint),
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::ClaimRewards {},
+    )
+}
+
+/// `offer_loan`, escrows `principal` lamports in the (lender, nonce) loan.
+pub fn offer_loan(
+    lender: &Pubkey,
+    nonce: u64,
+    collection: &Pubkey,
+    principal: u64,
+    apr_bps: u32,
+    duration: i64,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::OfferLoan {
+        loan: find_loan(lender, nonce),
+        lender: *lender,
+        config: pda::find_config().0,
+        collection_status: find_collection_status(Some(*collection)),
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::OfferLoan {
+            nonce,
+            collection: *collection,
+            principal,
+            apr_bps,
+            duration,
+        },
+    )
+}
+
+/// `take_loan`, escrows `borrower`'s item and pays them the principal. `collection` is the
+/// loan's collection.
+pub fn take_loan(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    collection: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = find_loan(lender, nonce);
+    let borrower_item_token = get_associated_token_address(borrower, mint);
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let accounts = pnft_transfer::accounts::TakeLoan {
+        item: *mint,
+        borrower_item_token,
+        loan,
+        loan_item_token,
+        borrower: *borrower,
+        config: pda::find_config().0,
+        collection_status: find_collection_status(Some(*collection)),
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        borrower_token_record: find_token_record(mint, &borrower_item_token),
+        loan_token_record: find_token_record(mint, &loan_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::TakeLoan {
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `repay_loan`, pays the lender back and returns the item to `borrower`'s ATA.
+pub fn repay_loan(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = find_loan(lender, nonce);
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let borrower_item_token = get_associated_token_address(borrower, mint);
+    let accounts = pnft_transfer::accounts::RepayLoan {
+        item: *mint,
+        loan,
+        loan_item_token,
+        lender: *lender,
+        borrower: *borrower,
+        borrower_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        loan_token_record: find_token_record(mint, &loan_item_token),
+        borrower_token_record: find_token_record(mint, &borrower_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::RepayLoan {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `foreclose_loan`, past the deadline the item goes to `lender`'s ATA.
+pub fn foreclose_loan(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = find_loan(lender, nonce);
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let lender_item_token = get_associated_token_address(lender, mint);
+    let accounts = pnft_transfer::accounts::ForecloseLoan {
+        item: *mint,
+        loan,
+        loan_item_token,
+        lender: *lender,
+        borrower: *borrower,
+        lender_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        loan_token_record: find_token_record(mint, &loan_item_token),
+        lender_token_record: find_token_record(mint, &lender_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ForecloseLoan {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `cancel_loan_offer`, only while nobody took it.
+pub fn cancel_loan_offer(lender: &Pubkey, nonce: u64) -> Instruction {
+    let accounts = pnft_transfer::accounts::CancelLoanOffer {
+        loan: find_loan(lender, nonce),
+        lender: *lender,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::CancelLoanOffer {},
+    )
+}
+
+/// `list_for_rent`, escrows `owner`'s item in its (mint, owner) rental. `collection` is the item's
+/// verified collection, for the marketplace controls.
+pub fn list_for_rent(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    daily_price: u64,
+    max_days: u16,
+    collection: Option<Pubkey>,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let rental = find_rental(mint, owner);
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let accounts = pnft_transfer::accounts::ListForRent {
+        item: *mint,
+        owner_item_token,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        config: pda::find_config().0,
+        collection_status: find_collection_status(collection),
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        owner_token_record: find_token_record(mint, &owner_item_token),
+        rental_token_record: find_token_record(mint, &rental_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ListForRent {
+            daily_price,
+            max_days,
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `cancel_rental`, only while it isn't rented, the item goes back to `owner`'s ATA.
+pub fn cancel_rental(mint: &Pubkey, owner: &Pubkey, rule_set: Option<Pubkey>) -> Instruction {
+    let rental = find_rental(mint, owner);
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::CancelRental {
+        item: *mint,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        owner_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        rental_token_record: find_token_record(mint, &rental_item_token),
+        owner_token_record: find_token_record(mint, &owner_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::CancelRental {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `rent_pnft`, `renter` pays for `days` and gets the item locked in their ATA. `collection` is
+/// the item's verified collection, for the marketplace controls.
+pub fn rent_pnft(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    renter: &Pubkey,
+    days: u16,
+    collection: Option<Pubkey>,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let rental = find_rental(mint, owner);
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let renter_item_token = get_associated_token_address(renter, mint);
+    let accounts = pnft_transfer::accounts::RentPNFT {
+        item: *mint,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        renter: *renter,
+        renter_item_token,
+        config: pda::find_config().0,
+        collection_status: find_collection_status(collection),
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        rental_token_record: find_token_record(mint, &rental_item_token),
+        renter_token_record: find_token_record(mint, &renter_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::RentPnft {
+            days,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `end_rental`, past `rented_until` anyone (`payer`) can send the item back to `owner`'s ATA.
+pub fn end_rental(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    renter: &Pubkey,
+    payer: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let renter_item_token = get_associated_token_address(renter, mint);
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::EndRental {
+        item: *mint,
+        rental: find_rental(mint, owner),
+        owner: *owner,
+        renter: *renter,
+        renter_item_token,
+        owner_item_token,
+        payer: *payer,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        renter_token_record: find_token_record(mint, &renter_item_token),
+        owner_token_record: find_token_record(mint, &owner_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::EndRental {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `list_bundle`, escrows every item in `items` under the (seller, nonce) bundle.
+pub fn list_bundle(seller: &Pubkey, nonce: u64, price: u64, items: &[BundleItem]) -> Instruction {
+    let bundle = find_bundle(seller, nonce);
+    let accounts = pnft_transfer::accounts::ListBundle {
+        bundle,
+        seller: *seller,
+        config: pda::find_config().0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        bundle_metas(items, seller, &bundle, true),
+        pnft_transfer::instruction::ListBundle {
+            nonce,
+            price,
+            authorization_data: None,
+        },
+    )
+}
+
+/// `buy_bundle`, `items` in the order they were listed.
+pub fn buy_bundle(seller: &Pubkey, nonce: u64, buyer: &Pubkey, items: &[BundleItem]) -> Instruction {
+    let bundle = find_bundle(seller, nonce);
+    let accounts = pnft_transfer::accounts::BuyBundle {
+        bundle,
+        seller: *seller,
+        buyer: *buyer,
+        config: pda::find_config().0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        bundle_metas(items, &bundle, buyer, true),
+        pnft_transfer::instruction::BuyBundle {},
+    )
+}
+
+/// `cancel_bundle`, `items` in the order they were listed. Their collections aren't needed.
+pub fn cancel_bundle(seller: &Pubkey, nonce: u64, items: &[BundleItem]) -> Instruction {
+    let bundle = find_bundle(seller, nonce);
+    let accounts = pnft_transfer::accounts::CancelBundle {
+        bundle,
+        seller: *seller,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        bundle_metas(items, &bundle, seller, false),
+        pnft_transfer::instruction::CancelBundle {},
+    )
+}
+
+/// `update_price` on the (mint, seller, nonce) listing.
+pub fn update_price(mint: &Pubkey, seller: &Pubkey, nonce: u64, new_price: u64) -> Instruction {
+    let accounts = pnft_transfer::accounts::UpdatePrice {
+        listing: find_listing(mint, seller, nonce),
+        seller: *seller,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::UpdatePrice { new_price },
+    )
+}
+
+/// `cancel_listing`, returns what's left in escrow to `seller`'s ATA.
+pub fn cancel_listing(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let listing = find_listing(mint, seller, nonce);
+    let listing_item_token = get_associated_token_address(&listing, mint);
+    let seller_item_token = get_associated_token_address(seller, mint);
+    let accounts = pnft_transfer::accounts::CancelListing {
+        item: *mint,
+        listing,
+        listing_item_token,
+        seller: *seller,
+        seller_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        listing_token_record: find_token_record(mint, &listing_item_token),
+        seller_token_record: find_token_record(mint, &seller_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::CancelListing {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `migrate_listing` for an account written before the Listing version byte.
+///
+/// Only handles seller + nonce seeded listings. Listings whose seeds predate the nonce can't be
+/// migrated, see [`unwind_legacy_listing`].
+pub fn migrate_listing(listing: &Pubkey, mint: &Pubkey, payer: &Pubkey) -> Instruction {
+    let accounts = pnft_transfer::accounts::MigrateListing {
+        listing: *listing,
+        item: *mint,
+        item_metadata: find_metadata(mint),
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::MigrateListing {},
+    )
+}
+
+/// `unwind_legacy_listing`, signed by the program's upgrade authority.
+///
+/// `legacy_listing` is [`pda::find_legacy_listing`] for listings seeded by the item alone, or
+/// [`pda::find_legacy_seller_listing`] for ones seeded by the item and seller.
+pub fn unwind_legacy_listing(
+    legacy_listing: &Pubkey,
+    mint: &Pubkey,
+    seller: &Pubkey,
+    upgrade_authority: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let legacy_listing = *legacy_listing;
+    let listing_item_token = get_associated_token_address(&legacy_listing, mint);
+    let seller_item_token = get_associated_token_address(seller, mint);
+    let program_data = pda::find_program_data().0;
+    let accounts = pnft_transfer::accounts::UnwindLegacyListing {
+        item: *mint,
+        legacy_listing,
+        listing_item_token,
+        seller: *seller,
+        seller_item_token,
+        authority: *upgrade_authority,
+        program: pnft_transfer::ID,
+        program_data,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        listing_token_record: find_token_record(mint, &listing_item_token),
+        seller_token_record: find_token_record(mint, &seller_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::UnwindLegacyListing {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `init_config`, signed by the program's upgrade authority, `authority` runs the marketplace
+/// controls from then on.
+pub fn init_config(upgrade_authority: &Pubkey, authority: &Pubkey) -> Instruction {
+    let accounts = pnft_transfer::accounts::InitConfig {
+        config: pda::find_config().0,
+        payer: *upgrade_authority,
+        program: pnft_transfer::ID,
+        program_data: pda::find_program_data().0,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::InitConfig {
+            authority: *authority,
+        },
+    )
+}
+
+/// `set_paused`, signed by the config's authority.
+pub fn set_paused(authority: &Pubkey, paused: bool) -> Instruction {
+    let accounts = pnft_transfer::accounts::UpdateConfig {
+        config: pda::find_config().0,
+        authority: *authority,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::SetPaused { paused },
+    )
+}
+
+/// `set_collection_status`, signed by the config's authority.
+pub fn set_collection_status(
+    authority: &Pubkey,
+    collection: &Pubkey,
+    status: CollectionState,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::SetCollectionStatus {
+        config: pda::find_config().0,
+        collection_status: find_collection_status(Some(*collection)),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::SetCollectionStatus {
+            collection: *collection,
+            status,
+        },
+    )
+}
+
+/// `init_vault`, `owner`'s vault with `threshold` of `approvers` co-signing withdrawals.
+pub fn init_vault(owner: &Pubkey, approvers: Vec<Pubkey>, threshold: u8) -> Instruction {
+    let accounts = pnft_transfer::accounts::InitVault {
+        vault: find_vault(owner),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::InitVault {
+            approvers,
+            threshold,
+        },
+    )
+}
+
+/// `deposit`, from `depositor`'s ATA into `owner`'s vault.
+pub fn deposit(
+    mint: &Pubkey,
+    depositor: &Pubkey,
+    owner: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let vault = find_vault(owner);
+    let depositor_item_token = get_associated_token_address(depositor, mint);
+    let vault_item_token = get_associated_token_address(&vault, mint);
+    let accounts = pnft_transfer::accounts::Deposit {
+        item: *mint,
+        depositor_item_token,
+        vault,
+        vault_item_token,
+        depositor: *depositor,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        depositor_token_record: find_token_record(mint, &depositor_item_token),
+        vault_token_record: find_token_record(mint, &vault_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::Deposit {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+/// `withdraw` / `withdraw_to` out of `owner`'s vault, `approvers` have to sign the txn as well.
+/// Goes to the owner's own ATA unless `receiver` is set.
+#[derive(Clone, Debug)]
+pub struct Withdraw {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub receiver: Option<Pubkey>,
+    pub rule_set: Option<Pubkey>,
+    pub approvers: Vec<Pubkey>,
+}
+
+impl Withdraw {
+    pub fn new(mint: Pubkey, owner: Pubkey) -> Self {
+        Self {
+            mint,
+            owner,
+            receiver: None,
+            rule_set: None,
+            approvers: vec![],
+        }
+    }
+
+    pub fn to(mut self, receiver: Pubkey) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    pub fn rule_set(mut self, rule_set: Option<Pubkey>) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn fetch_rule_set(self, fetcher: &impl AccountFetcher) -> Result<Self, ClientError> {
+        let rule_set = fetch_rule_set(fetcher, &self.mint)?;
+        Ok(self.rule_set(rule_set))
+    }
+
+    pub fn approvers(mut self, approvers: Vec<Pubkey>) -> Self {
+        self.approvers = approvers;
+        self
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let vault = find_vault(&self.owner);
+        let receiver = self.receiver.unwrap_or(self.owner);
+        let vault_item_token = get_associated_token_address(&vault, &self.mint);
+        let receiver_item_token = get_associated_token_address(&receiver, &self.mint);
+        let accounts = pnft_transfer::accounts::Withdraw {
+            item: self.mint,
+            vault,
+            vault_item_token,
+            owner: self.owner,
+            receiver,
+            receiver_item_token,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            associated_token_program: associated_token::ID,
+            item_metadata: find_metadata(&self.mint),
+            edition: find_edition(&self.mint),
+            vault_token_record: find_token_record(&self.mint, &vault_item_token),
+            receiver_token_record: find_token_record(&self.mint, &receiver_item_token),
+            token_metadata_program: mpl_token_metadata::ID,
+            instructions: sysvar::instructions::ID,
+            authorization_rules_program: mpl_token_auth_rules::ID,
+        };
+        let mut remaining = rule_set_metas(self.rule_set);
+        remaining.extend(
+            self.approvers
+                .iter()
+                .map(|approver| anchor_lang::prelude::AccountMeta::new_readonly(*approver, true)),
+        );
+        let rules_acc_present = self.rule_set.is_some();
+        match self.receiver {
+            Some(_) => instruction(
+                accounts.to_account_metas(None),
+                remaining,
+                pnft_transfer::instruction::WithdrawTo { rules_acc_present },
+            ),
+            None => instruction(
+                accounts.to_account_metas(None),
+                remaining,
+                pnft_transfer::instruction::Withdraw { rules_acc_present },
+            ),
+        }
+    }
+}
+
+/// `create_multisig`, with its vault at `find_vault(multisig)` for `deposit`s.
+pub fn create_multisig(
+    creator: &Pubkey,
+    nonce: u64,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+    timelock: i64,
+) -> Instruction {
+    let multisig = find_multisig(creator, nonce);
+    let accounts = pnft_transfer::accounts::CreateMultisig {
+        multisig,
+        vault: find_vault(&multisig),
+        creator: *creator,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::CreateMultisig {
+            nonce,
+            signers,
+            threshold,
+            timelock,
+        },
+    )
+}
+
+/// `propose_withdrawal` of `mint` to `receiver`, `index` is the multisig's `proposal_count`.
+pub fn propose_withdrawal(
+    multisig: &Pubkey,
+    index: u64,
+    proposer: &Pubkey,
+    mint: &Pubkey,
+    receiver: &Pubkey,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::ProposeWithdrawal {
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        proposer: *proposer,
+        system_program: system_program::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::ProposeWithdrawal {
+            item: *mint,
+            receiver: *receiver,
+        },
+    )
+}
+
+/// `approve`, signed by one of the multisig's signers.
+pub fn approve(multisig: &Pubkey, index: u64, approver: &Pubkey) -> Instruction {
+    let accounts = pnft_transfer::accounts::Approve {
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        approver: *approver,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::Approve {},
+    )
+}
+
+/// `execute_withdrawal`, anyone can send it once the proposal is approved and the timelock is up.
+pub fn execute_withdrawal(
+    multisig: &Pubkey,
+    index: u64,
+    proposer: &Pubkey,
+    mint: &Pubkey,
+    receiver: &Pubkey,
+    payer: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let vault = find_vault(multisig);
+    let vault_item_token = get_associated_token_address(&vault, mint);
+    let receiver_item_token = get_associated_token_address(receiver, mint);
+    let accounts = pnft_transfer::accounts::ExecuteWithdrawal {
+        item: *mint,
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        vault,
+        vault_item_token,
+        proposer: *proposer,
+        receiver: *receiver,
+        receiver_item_token,
+        payer: *payer,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: find_metadata(mint),
+        edition: find_edition(mint),
+        vault_token_record: find_token_record(mint, &vault_item_token),
+        receiver_token_record: find_token_record(mint, &receiver_item_token),
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    instruction(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ExecuteWithdrawal {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
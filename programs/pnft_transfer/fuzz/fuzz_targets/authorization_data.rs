@@ -0,0 +1,39 @@
+#![no_main]
+// Arbitrary bytes as transfer_pnft's ix args (what anchor deserializes after the discriminator),
+// then the AuthorizationDataLocal -> metaplex AuthorizationData conversion send_pnft does.
+// Has to either reject the bytes or convert without panicking, keeping every payload entry.
+//
+//   cargo +nightly fuzz run authorization_data
+use anchor_lang::AnchorDeserialize;
+use libfuzzer_sys::fuzz_target;
+use mpl_token_auth_rules::payload::{Payload, PayloadType, ProofInfo, SeedsVec};
+use mpl_token_metadata::processor::AuthorizationData;
+use pnft_transfer::{instruction::TransferPnft, PayloadTypeLocal};
+
+fuzz_target!(|data: &[u8]| {
+    let args = match TransferPnft::deserialize(&mut &data[..]) {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+    let local = match args.authorization_data {
+        Some(local) => local,
+        None => return,
+    };
+
+    // built independently of the From impls, later entries win on duplicate names
+    let mut expected = Payload::new();
+    for tp in local.payload.iter() {
+        let payload = match tp.payload.clone() {
+            PayloadTypeLocal::Pubkey(pubkey) => PayloadType::Pubkey(pubkey),
+            PayloadTypeLocal::Seeds(seeds) => PayloadType::Seeds(SeedsVec::new(seeds.seeds)),
+            PayloadTypeLocal::MerkleProof(proof) => {
+                PayloadType::MerkleProof(ProofInfo::new(proof.proof))
+            }
+            PayloadTypeLocal::Number(number) => PayloadType::Number(number),
+        };
+        expected.insert(tp.name.clone(), payload);
+    }
+
+    let converted = AuthorizationData::from(local);
+    assert_eq!(converted.payload, expected);
+});
@@ -0,0 +1,89 @@
+use crate::cpi::accounts::{ProgNftShared, TransferPNFT};
+use crate::*;
+
+// Wrappers around the generated cpi::transfer_pnft, for programs that want to move pNFTs through
+// this one. They flatten ProgNftShared and take care of the optional ruleset remaining account.
+//
+// If `owner` is a PDA of the calling program use transfer_pnft_signed. `payer` covers the
+// destination's token record instead of `owner`, so it has to be a system account with enough
+// lamports (payer can't carry data) that signs.
+
+pub struct TransferPnftAccounts<'info> {
+    pub owner: AccountInfo<'info>,
+    // None has the owner pay
+    pub payer: Option<AccountInfo<'info>>,
+    pub receiver: AccountInfo<'info>,
+    pub src: AccountInfo<'info>,
+    pub dest: AccountInfo<'info>,
+    pub nft_mint: AccountInfo<'info>,
+    pub nft_metadata: AccountInfo<'info>,
+    pub edition: AccountInfo<'info>,
+    pub owner_token_record: AccountInfo<'info>,
+    pub dest_token_record: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub token_metadata_program: AccountInfo<'info>,
+    pub instructions: AccountInfo<'info>,
+    pub authorization_rules_program: AccountInfo<'info>,
+    // required when the item's metadata has a ruleset, see metadata_rule_set
+    pub rule_set: Option<AccountInfo<'info>>,
+}
+
+pub fn transfer_pnft<'info>(
+    pnft_transfer_program: AccountInfo<'info>,
+    accounts: TransferPnftAccounts<'info>,
+    authorization_data: Option<AuthorizationDataLocal>,
+) -> Result<()> {
+    transfer_pnft_signed(pnft_transfer_program, accounts, authorization_data, &[])
+}
+
+pub fn transfer_pnft_signed<'info>(
+    pnft_transfer_program: AccountInfo<'info>,
+    accounts: TransferPnftAccounts<'info>,
+    authorization_data: Option<AuthorizationDataLocal>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let rules_acc_present = accounts.rule_set.is_some();
+    let remaining_accounts = accounts
+        .rule_set
+        .into_iter()
+        .chain(accounts.payer)
+        .collect();
+
+    let cpi_accounts = TransferPNFT {
+        owner: accounts.owner,
+        receiver: accounts.receiver,
+        src: accounts.src,
+        dest: accounts.dest,
+        nft_mint: accounts.nft_mint,
+        token_program: accounts.token_program,
+        system_program: accounts.system_program,
+        rent: accounts.rent,
+        associated_token_program: accounts.associated_token_program,
+        nft_metadata: accounts.nft_metadata,
+        edition: accounts.edition,
+        owner_token_record: accounts.owner_token_record,
+        dest_token_record: accounts.dest_token_record,
+        pnft_shared: ProgNftShared {
+            token_metadata_program: accounts.token_metadata_program,
+            instructions: accounts.instructions,
+            authorization_rules_program: accounts.authorization_rules_program,
+        },
+    };
+
+    let ctx = CpiContext::new_with_signer(pnft_transfer_program, cpi_accounts, signer_seeds)
+        .with_remaining_accounts(remaining_accounts);
+    crate::cpi::transfer_pnft(ctx, authorization_data, rules_acc_present, None)
+}
+
+// the ruleset the item's metadata points to, pass the matching account as TransferPnftAccounts::rule_set
+pub fn metadata_rule_set(nft_metadata: &AccountInfo) -> Result<Option<Pubkey>> {
+    require_keys_eq!(*nft_metadata.owner, mpl_token_metadata::id(), ErrorCode::BadMetadata);
+    let metadata = Metadata::from_account_info(nft_metadata)?;
+    Ok(match metadata.programmable_config {
+        Some(V1 { rule_set }) => rule_set,
+        None => None,
+    })
+}
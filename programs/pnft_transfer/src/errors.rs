@@ -9,5 +9,84 @@ pub enum ErrorCode {
     #[msg("InvalidItem")]
     InvalidItem,
     #[msg("TransferBuilderFailed")]
-    TransferBuilderFailed
+    TransferBuilderFailed,
+    // unused since listings are 1/1 only, kept so the codes after it don't shift
+    #[msg("InvalidQuantity")]
+    InvalidQuantity,
+    #[msg("ArithmeticOverflow")]
+    ArithmeticOverflow,
+    #[msg("BadListing")]
+    BadListing,
+    #[msg("ListingAlreadyMigrated")]
+    ListingAlreadyMigrated,
+    #[msg("InstructionBuilderFailed")]
+    InstructionBuilderFailed,
+    #[msg("NotProgrammable")]
+    NotProgrammable,
+    #[msg("TooManyMultipliers")]
+    TooManyMultipliers,
+    #[msg("WrongRewardPool")]
+    WrongRewardPool,
+    #[msg("CollectionMismatch")]
+    CollectionMismatch,
+    #[msg("InvalidLoanTerms")]
+    InvalidLoanTerms,
+    #[msg("LoanAlreadyTaken")]
+    LoanAlreadyTaken,
+    #[msg("LoanNotOverdue")]
+    LoanNotOverdue,
+    #[msg("InsufficientFunds")]
+    InsufficientFunds,
+    #[msg("InvalidRentalDays")]
+    InvalidRentalDays,
+    #[msg("AlreadyRented")]
+    AlreadyRented,
+    #[msg("NotRented")]
+    NotRented,
+    #[msg("RentalNotExpired")]
+    RentalNotExpired,
+    #[msg("InvalidBundleSize")]
+    InvalidBundleSize,
+    #[msg("BundleItemMismatch")]
+    BundleItemMismatch,
+    #[msg("MarketPaused")]
+    MarketPaused,
+    #[msg("CollectionFrozen")]
+    CollectionFrozen,
+    #[msg("BadCollectionStatus")]
+    BadCollectionStatus,
+    #[msg("BadEdition")]
+    BadEdition,
+    #[msg("PriceMismatch")]
+    PriceMismatch,
+    #[msg("SellerMismatch")]
+    SellerMismatch,
+    #[msg("NonceMismatch")]
+    NonceMismatch,
+    #[msg("MemoTooLong")]
+    MemoTooLong,
+    #[msg("InvalidThreshold")]
+    InvalidThreshold,
+    #[msg("NotEnoughApprovals")]
+    NotEnoughApprovals,
+    #[msg("BadReceiver")]
+    BadReceiver,
+    #[msg("InvalidTimelock")]
+    InvalidTimelock,
+    #[msg("NotASigner")]
+    NotASigner,
+    #[msg("AlreadyApproved")]
+    AlreadyApproved,
+    #[msg("TimelockActive")]
+    TimelockActive,
+    #[msg("LegacyListingLayout")]
+    LegacyListingLayout,
+    #[msg("InvalidMultiplier")]
+    InvalidMultiplier,
+    #[msg("CollectionNotRewarded")]
+    CollectionNotRewarded,
+    #[msg("BadPayer")]
+    BadPayer,
+    #[msg("ListingNotMigrated")]
+    ListingNotMigrated,
 }
@@ -0,0 +1,274 @@
+use anchor_lang::prelude::*;
+use crate::CollectionState;
+
+// settlement is in SOL only for now so payment_mint is always the native mint
+
+#[event]
+pub struct Listed {
+    pub listing: Pubkey,
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct PriceUpdated {
+    pub listing: Pubkey,
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    pub old_price: u64,
+    pub new_price: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct Sold {
+    pub listing: Pubkey,
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct Cancelled {
+    pub listing: Pubkey,
+    pub mint: Pubkey,
+    pub seller: Pubkey,
+    // units returned to the seller
+    pub quantity: u64,
+}
+
+#[event]
+pub struct Transferred {
+    pub mint: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    // caller's reference (e.g. an order id), if they passed one
+    pub memo: Option<String>,
+}
+
+#[event]
+pub struct Staked {
+    pub stake_record: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub staked_at: i64,
+}
+
+#[event]
+pub struct Unstaked {
+    pub stake_record: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub staked_at: i64,
+    pub unstaked_at: i64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub reward_pool: Pubkey,
+    pub stake_record: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    // in reward mint base units
+    pub amount: u64,
+    // the period paid out, unix timestamps
+    pub from: i64,
+    pub to: i64,
+}
+
+// loan amounts are in lamports
+
+#[event]
+pub struct LoanOffered {
+    pub loan: Pubkey,
+    pub lender: Pubkey,
+    pub collection: Pubkey,
+    pub principal: u64,
+    pub apr_bps: u32,
+    pub duration: i64,
+}
+
+#[event]
+pub struct LoanOfferCancelled {
+    pub loan: Pubkey,
+    pub lender: Pubkey,
+}
+
+#[event]
+pub struct LoanTaken {
+    pub loan: Pubkey,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub principal: u64,
+    // unix timestamp the lender can foreclose after
+    pub due_at: i64,
+}
+
+#[event]
+pub struct LoanRepaid {
+    pub loan: Pubkey,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub principal: u64,
+    pub interest: u64,
+}
+
+#[event]
+pub struct LoanForeclosed {
+    pub loan: Pubkey,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+}
+
+// rental prices are in lamports
+
+#[event]
+pub struct ListedForRent {
+    pub rental: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub daily_price: u64,
+    pub max_days: u16,
+}
+
+#[event]
+pub struct RentalCancelled {
+    pub rental: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+}
+
+#[event]
+pub struct Rented {
+    pub rental: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub renter: Pubkey,
+    pub days: u16,
+    // paid up front, daily_price * days
+    pub price: u64,
+    // unix timestamp anyone can end_rental after
+    pub rented_until: i64,
+}
+
+#[event]
+pub struct RentalEnded {
+    pub rental: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub renter: Pubkey,
+}
+
+#[event]
+pub struct BundleListed {
+    pub bundle: Pubkey,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    // for the whole lot, in lamports
+    pub price: u64,
+    pub items: Vec<Pubkey>,
+}
+
+#[event]
+pub struct BundleSold {
+    pub bundle: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub items: Vec<Pubkey>,
+}
+
+#[event]
+pub struct BundleCancelled {
+    pub bundle: Pubkey,
+    pub seller: Pubkey,
+    pub items: Vec<Pubkey>,
+}
+
+#[event]
+pub struct PauseToggled {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct CollectionStatusSet {
+    pub collection_status: Pubkey,
+    pub collection: Pubkey,
+    pub authority: Pubkey,
+    pub status: CollectionState,
+}
+
+#[event]
+pub struct VaultCreated {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub approvers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct VaultDeposited {
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    // whoever the item came from, not necessarily the vault's owner
+    pub depositor: Pubkey,
+}
+
+#[event]
+pub struct VaultWithdrawn {
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub receiver: Pubkey,
+}
+
+#[event]
+pub struct MultisigCreated {
+    pub multisig: Pubkey,
+    // its vault, what deposits go to
+    pub vault: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    // seconds
+    pub timelock: i64,
+}
+
+#[event]
+pub struct WithdrawalProposed {
+    pub proposal: Pubkey,
+    pub multisig: Pubkey,
+    pub proposer: Pubkey,
+    pub mint: Pubkey,
+    pub receiver: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalApproved {
+    pub proposal: Pubkey,
+    pub multisig: Pubkey,
+    pub approver: Pubkey,
+    // including this one
+    pub approvals: u8,
+    // unix timestamp it can be executed from, 0 until the threshold is met
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub proposal: Pubkey,
+    pub multisig: Pubkey,
+    pub mint: Pubkey,
+    pub receiver: Pubkey,
+}
@@ -1,49 +1,96 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::{
+    program::{invoke, invoke_signed},
+    program_option::COption,
+};
+use anchor_lang::system_program;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    associated_token::{self, AssociatedToken},
+    token::{self, spl_token::native_mint, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer},
 };
 use mpl_token_auth_rules::payload::{Payload, PayloadType, ProofInfo, SeedsVec};
 use mpl_token_metadata::{
     self,
-    instruction::{builders::TransferBuilder, InstructionBuilder, TransferArgs},
+    instruction::{
+        builders::{DelegateBuilder, LockBuilder, RevokeBuilder, TransferBuilder, UnlockBuilder},
+        DelegateArgs, InstructionBuilder, LockArgs, RevokeArgs, TransferArgs, UnlockArgs,
+    },
     processor::AuthorizationData,
-    state::{Metadata, ProgrammableConfig::V1, TokenMetadataAccount, TokenStandard},
+    state::{
+        MasterEditionV2, Metadata, PayloadKey, ProgrammableConfig::V1, TokenMetadataAccount,
+        TokenStandard,
+    },
 };
+// verbose logging for local debugging, compiled out unless built with the debug-logs feature
+macro_rules! debug_msg {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug-logs")]
+        msg!($($arg)*);
+    };
+}
+
+#[cfg(feature = "cpi")]
+pub mod cpi_helpers;
 pub mod errors;
+pub mod events;
+pub mod pda;
 pub mod utils;
 
 use errors::ErrorCode;
+use events::*;
+use pda::{
+    BUNDLE_SEED, COLLECTION_STATUS_SEED, CONFIG_SEED, LISTING_SEED, LOAN_SEED, RENTAL_SEED,
+    MULTISIG_SEED, PROPOSAL_SEED, REWARD_POOL_SEED, STAKE_SEED, VAULT_SEED,
+};
 use utils::*;
 
 declare_id!("4VL7z3sVLTEUt6NCbey5FxWSvwQrN7Yf9LXXjZz538wA");
 
 #[program]
 pub mod pnft_transfer {
-    use mpl_token_metadata::state::PayloadKey;
     use super::*;
 
+    // memo is for matching transfers to off-chain orders, it's in the Transferred event and, if
+    // the SPL Memo program is passed, written to the txn by a Memo CPI signed by the owner
     pub fn transfer_pnft<'info>(
         ctx: Context<'_, '_, '_, 'info, TransferPNFT<'info>>,
         authorization_data: Option<AuthorizationDataLocal>,
         rules_acc_present: bool,
+        memo: Option<String>,
     ) -> Result<()> {
-        let rem_acc = &mut ctx.remaining_accounts.iter();
+        if let Some(memo) = &memo {
+            require!(memo.len() <= MAX_MEMO_LEN, ErrorCode::MemoTooLong);
+        }
+
+        let rem_acc = &mut ctx.remaining_accounts.iter().peekable();
         let auth_rules = if rules_acc_present {
             Some(next_account_info(rem_acc)?)
         } else {
             None
         };
+        let memo_program = rem_acc.next_if(|acc| acc.key() == spl_memo::id());
+        // a sponsor (e.g. a relayer) covering the dest token record rent goes last and has to
+        // sign. Without one the owner pays
+        let owner = ctx.accounts.owner.to_account_info();
+        let payer = match rem_acc.next() {
+            Some(payer) => {
+                require!(payer.is_signer && payer.is_writable, ErrorCode::BadPayer);
+                payer
+            }
+            None => &owner,
+        };
         send_pnft(
-            &ctx.accounts.owner.to_account_info(),
-            &ctx.accounts.owner.to_account_info(),
+            &owner,
+            None,
+            payer,
             &ctx.accounts.src,
             &ctx.accounts.dest,
             &ctx.accounts.receiver.to_account_info(),
             &ctx.accounts.nft_mint,
             &ctx.accounts.nft_metadata,
             &ctx.accounts.edition,
+            &ctx.accounts.pnft_shared.token_metadata_program,
             &ctx.accounts.system_program,
             &ctx.accounts.token_program,
             &ctx.accounts.associated_token_program,
@@ -53,8 +100,22 @@ pub mod pnft_transfer {
             &ctx.accounts.pnft_shared.authorization_rules_program,
             auth_rules,
             authorization_data,
-            // None,
+            1,
+            None,
         )?;
+
+        if let (Some(memo), Some(memo_program)) = (&memo, memo_program) {
+            write_memo(memo, &ctx.accounts.owner.to_account_info(), memo_program)?;
+        }
+
+        emit!(Transferred {
+            mint: ctx.accounts.nft_mint.key(),
+            from: ctx.accounts.owner.key(),
+            to: ctx.accounts.receiver.key(),
+            amount: 1,
+            memo,
+        });
+
         Ok(())
     }
 
@@ -62,12 +123,20 @@ pub mod pnft_transfer {
     pub fn list_pnft<'info>(
         ctx: Context<'_, '_, '_, 'info, ListPNFT<'info>>,
         price: u64,
+        nonce: u64,
         authorization_data: Option<AuthorizationDataLocal>,
         rules_acc_present: bool,
     ) -> Result<()> {
 
-        // make sure the item exists in the from account
-        require!(ctx.accounts.authority_item_token.amount == 1, ErrorCode::InvalidItem);
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        assert_pnft_master_edition(&ctx.accounts.item, &metadata, &ctx.accounts.edition)?;
+        let collection = verified_collection(&metadata);
+        assert_trading_allowed(&ctx.accounts.config, &ctx.accounts.collection_status, &collection)?;
+
+        // make sure the item exists in the from account. The edition check above pins supply to
+        // 1, so a listing is always the one unit
+        require!(ctx.accounts.authority_item_token.amount > 0, ErrorCode::InvalidItem);
 
         // first, transfer the item to the listing ata
         let rem_acc = &mut ctx.remaining_accounts.iter();
@@ -78,6 +147,7 @@ pub mod pnft_transfer {
         };
         send_pnft(
             &ctx.accounts.authority.to_account_info(),
+            None,
             &ctx.accounts.authority.to_account_info(),
             &ctx.accounts.authority_item_token,
             &ctx.accounts.listing_item_token,
@@ -85,6 +155,7 @@ pub mod pnft_transfer {
             &ctx.accounts.item,
             &ctx.accounts.item_metadata,
             &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
             &ctx.accounts.system_program,
             &ctx.accounts.token_program,
             &ctx.accounts.associated_token_program,
@@ -94,6 +165,8 @@ pub mod pnft_transfer {
             &ctx.accounts.authorization_rules_program,
             auth_rules,
             authorization_data,
+            1,
+            None,
         )?;
 
         // now create the listing
@@ -101,273 +174,3461 @@ pub mod pnft_transfer {
         listing.item = ctx.accounts.item.key();
         listing.item_token = ctx.accounts.listing_item_token.key();
         listing.bump = *ctx.bumps.get("listing").unwrap();
+        listing.seller = ctx.accounts.authority.key();
+        listing.collection = collection;
+        listing.price = price;
+        listing.nonce = nonce;
+        listing.version = LISTING_VERSION;
+
+        emit!(Listed {
+            listing: listing.key(),
+            mint: listing.item,
+            seller: listing.seller,
+            nonce,
+            price,
+            payment_mint: native_mint::ID,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_price(ctx: Context<UpdatePrice>, new_price: u64) -> Result<()> {
+        let listing = &mut ctx.accounts.listing;
+        let old_price = listing.price;
+        listing.price = new_price;
+
+        emit!(PriceUpdated {
+            listing: listing.key(),
+            mint: listing.item,
+            seller: listing.seller,
+            old_price,
+            new_price,
+            payment_mint: native_mint::ID,
+        });
+
+        Ok(())
+    }
+
+    // returns whatever is left in escrow to the seller and closes the listing
+    pub fn cancel_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelListing<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+
+        let listing = &ctx.accounts.listing;
+        let auth_data = AuthorizationDataLocal::seeds(
+            PayloadKey::SourceSeeds,
+            vec![
+                listing.item.as_ref().to_vec(),
+                listing.seller.as_ref().to_vec(),
+                listing.nonce.to_le_bytes().to_vec(),
+                LISTING_SEED.to_vec(),
+            ],
+        );
+
+        let nonce = listing.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            listing.item.as_ref(),
+            listing.seller.as_ref(),
+            nonce.as_ref(),
+            LISTING_SEED,
+            &[listing.bump],
+        ]];
+
+        let quantity = ctx.accounts.listing_item_token.amount;
+        send_pnft(
+            &listing.to_account_info(),
+            None,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.seller_item_token,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.listing_token_record,
+            &ctx.accounts.seller_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            quantity,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.seller.to_account_info(),
+            &listing.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(Cancelled {
+            listing: listing.key(),
+            mint: listing.item,
+            seller: listing.seller,
+            quantity,
+        });
 
+        // listing is closed to the seller by its constraint
         Ok(())
     }
 
 
+    // expected_* are what the buyer saw when signing, the seller could have repriced (or
+    // cancelled and relisted) since
     pub fn buy_pnft<'info>(
         ctx: Context<'_, '_, '_, 'info, BuyPNFT<'info>>,
+        expected_price: u64,
+        expected_seller: Pubkey,
+        expected_nonce: Option<u64>,
     ) -> Result<()> {
-        msg!("Withdraw");
-
-
-        /*
-        let account_iter = &mut accounts.iter();
-        let authority_info = next_account_info(account_iter)?;
-        let rooster_pda_info = next_account_info(account_iter)?;
-        let token_info = next_account_info(account_iter)?;
-        let destination_owner_info = next_account_info(account_iter)?;
-        let destination_info = next_account_info(account_iter)?;
-        let mint_info = next_account_info(account_iter)?;
-        let metadata_info = next_account_info(account_iter)?;
-        let edition_info = next_account_info(account_iter)?;
-        let owner_token_record_info = next_account_info(account_iter)?;
-        let destination_token_record_info = next_account_info(account_iter)?;
-        let token_metadata_program_info = next_account_info(account_iter)?;
-        let system_program_info = next_account_info(account_iter)?;
-        let sysvar_instructions_info = next_account_info(account_iter)?;
-        let spl_token_program_info = next_account_info(account_iter)?;
-        let spl_ata_program_info = next_account_info(account_iter)?;
-        let mpl_token_auth_rules_program_info = next_account_info(account_iter)?;
-        let rule_set_info = next_account_info(account_iter)?;
-
-         */
-
         let listing = &ctx.accounts.listing;
+        require!(listing.price == expected_price, ErrorCode::PriceMismatch);
+        require_keys_eq!(listing.seller, expected_seller, ErrorCode::SellerMismatch);
+        if let Some(expected_nonce) = expected_nonce {
+            require!(listing.nonce == expected_nonce, ErrorCode::NonceMismatch);
+        }
+        assert_trading_allowed(
+            &ctx.accounts.config,
+            &ctx.accounts.collection_status,
+            &listing.collection,
+        )?;
+
+        // pay the seller first
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: ctx.accounts.seller.to_account_info(),
+                },
+            ),
+            listing.price,
+        )?;
+
+        // the listing PDA owns the escrowed item, so rulesets need its seeds to approve the transfer
+        let auth_data = AuthorizationDataLocal::seeds(
+            PayloadKey::SourceSeeds,
+            vec![
+                listing.item.as_ref().to_vec(),
+                listing.seller.as_ref().to_vec(),
+                listing.nonce.to_le_bytes().to_vec(),
+                LISTING_SEED.to_vec(),
+            ],
+        );
+
+        let nonce = listing.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            listing.item.as_ref(),
+            listing.seller.as_ref(),
+            nonce.as_ref(),
+            LISTING_SEED,
+            &[listing.bump],
+        ]];
+
+        send_pnft(
+            &listing.to_account_info(),
+            None,
+            &ctx.accounts.buyer.to_account_info(),
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.buyer_item_token,
+            &ctx.accounts.buyer.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.listing_token_record,
+            &ctx.accounts.buyer_token_record,
+            &ctx.accounts.authorization_rules_program,
+            Some(&ctx.accounts.ruleset.to_account_info()),
+            Some(auth_data),
+            // the one unit, unless it's a lot from before listings were 1/1 only, sold as a whole
+            ctx.accounts.listing_item_token.amount,
+            Some(signer_seeds),
+        )?;
+
+        // the listing is done, all of its rent goes back to the seller.
+        // (the escrow's token record is owned by TM, which has no way to close it yet, so that stays)
+        close_token_account(
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.seller.to_account_info(),
+            &listing.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(Sold {
+            listing: listing.key(),
+            mint: listing.item,
+            seller: listing.seller,
+            buyer: ctx.accounts.buyer.key(),
+            price: listing.price,
+            payment_mint: native_mint::ID,
+        });
+
+        // listing is closed to the seller by its constraint
+        Ok(())
+    }
+
+    // brings seller + nonce seeded listings written before the current layout up to it. Three
+    // layouts get here: LEGACY_LISTING_SIZE ones from before collection was inserted after
+    // seller, PRE_VERSION_LISTING_SIZE ones with collection but no version / reserved, and
+    // version 1 ones that still had a quantity. The payer covers any extra rent, a listing that
+    // shrinks keeps the surplus and it goes back to the seller with the rest when it's closed.
+    // Listings from before nonce was in the seeds (the baseline [item, "listings"] ones and the
+    // [item, seller, "listings"] ones after them) can't have a Listing's address, those go
+    // through unwind_legacy_listing instead
+    pub fn migrate_listing(ctx: Context<MigrateListing>) -> Result<()> {
+        let listing_info = ctx.accounts.listing.to_account_info();
+        let len = listing_info.data_len();
+        require!(len != Listing::SIZE, ErrorCode::ListingAlreadyMigrated);
+
+        let old = {
+            let data = listing_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == <Listing as Discriminator>::DISCRIMINATOR,
+                ErrorCode::BadListing
+            );
+            let mut fields = &data[8..];
+            match len {
+                LEGACY_LISTING_SIZE => PreCollectionListing::deserialize(&mut fields)?.into(),
+                PRE_VERSION_LISTING_SIZE | LISTING_V1_SIZE => ListingV1::deserialize(&mut fields)?,
+                _ => return err!(ErrorCode::BadListing),
+            }
+        };
+
+        // the stored seller + nonce have to be the ones the account was derived from
+        let nonce = old.nonce.to_le_bytes();
+        let derived = Pubkey::create_program_address(
+            &[
+                old.item.as_ref(),
+                old.seller.as_ref(),
+                nonce.as_ref(),
+                LISTING_SEED,
+                &[old.bump],
+            ],
+            ctx.program_id,
+        )
+        .ok();
+        if derived != Some(listing_info.key()) {
+            // at LEGACY_LISTING_SIZE that's a listing from before nonce was in the seeds
+            return if len == LEGACY_LISTING_SIZE {
+                err!(ErrorCode::LegacyListingLayout)
+            } else {
+                err!(ErrorCode::BadListing)
+            };
+        }
+        require_keys_eq!(old.item, ctx.accounts.item.key(), ErrorCode::InvalidItem);
+
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        let listing = Listing {
+            bump: old.bump,
+            item: old.item,
+            item_token: old.item_token,
+            seller: old.seller,
+            collection: verified_collection(&metadata),
+            nonce: old.nonce,
+            // a listing sells everything in escrow now, lots were priced per unit
+            price: old
+                .price
+                .checked_mul(old.quantity)
+                .ok_or(ErrorCode::ArithmeticOverflow)?,
+            version: LISTING_VERSION,
+            reserved: [0; LISTING_RESERVED_SIZE],
+        };
+
+        let rent_due = Rent::get()?
+            .minimum_balance(Listing::SIZE)
+            .saturating_sub(listing_info.lamports());
+        if rent_due > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: listing_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        listing_info.realloc(Listing::SIZE, true)?;
+        listing.try_serialize(&mut &mut listing_info.try_borrow_mut_data()?[..])?;
+
+        Ok(())
+    }
+
+    // listings created before nonce was part of the seeds can't be migrated to a Listing, whose
+    // address needs one, so the item goes back to the seller and they can relist under the new
+    // scheme. The [item, seller, "listings"] ones record their seller, the baseline
+    // [item, "listings"] ones don't and the upgrade authority attests who it is
+    pub fn unwind_legacy_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnwindLegacyListing<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+
+        let legacy_listing = &ctx.accounts.legacy_listing;
+        if legacy_listing.seller != Pubkey::default() {
+            require_keys_eq!(
+                legacy_listing.seller,
+                ctx.accounts.seller.key(),
+                ErrorCode::SellerMismatch
+            );
+        }
+        let seeds = legacy_listing.seeds();
+        let auth_data = AuthorizationDataLocal::seeds(
+            PayloadKey::SourceSeeds,
+            seeds.iter().map(|seed| seed.to_vec()).collect(),
+        );
+        let bump = [legacy_listing.bump];
+        let seeds_with_bump = [seeds.as_slice(), &[bump.as_ref()]].concat();
+        let expected = Pubkey::create_program_address(&seeds_with_bump, ctx.program_id)
+            .map_err(|_| error!(ErrorCode::BadListing))?;
+        require_keys_eq!(expected, legacy_listing.key(), ErrorCode::BadListing);
+        let signer_seeds: &[&[&[u8]]] = &[seeds_with_bump.as_slice()];
+
+        send_pnft(
+            &legacy_listing.to_account_info(),
+            None,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.seller_item_token,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.listing_token_record,
+            &ctx.accounts.seller_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            ctx.accounts.listing_item_token.amount,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.listing_item_token,
+            &ctx.accounts.seller.to_account_info(),
+            &legacy_listing.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
 
+        emit!(Cancelled {
+            listing: legacy_listing.key(),
+            mint: legacy_listing.item,
+            seller: ctx.accounts.seller.key(),
+            quantity: ctx.accounts.listing_item_token.amount,
+        });
 
-        // let auth_data = Some(AuthorizationData::try_from(authorization_data).unwrap());
+        // legacy_listing is closed to the seller by its constraint
+        Ok(())
+    }
 
-        let seeds = SeedsVec {
-            seeds: vec![ctx.accounts.item.key().as_ref().to_vec(), String::from("listings").as_bytes().to_vec()]
+    // locks the item in the owner's wallet, it can't be transferred or listed until unstaked.
+    // The stake record is the item's Staking delegate, so only this program can unlock it
+    pub fn stake_pnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, StakePNFT<'info>>,
+        authorization_data: Option<AuthorizationDataLocal>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
         };
 
-        let mut payload = Payload::new();
-        payload.insert(PayloadKey::SourceSeeds.to_string(), PayloadType::Seeds(seeds));
+        // Staking delegates and locking in place only exist for pNFTs
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        require!(
+            metadata.token_standard == Some(TokenStandard::ProgrammableNonFungible),
+            ErrorCode::NotProgrammable
+        );
+        let rules_acc = item_rules_acc(&metadata, auth_rules)?;
+
+        let bump = *ctx.bumps.get("stake_record").unwrap();
+        let item = ctx.accounts.item.key();
+        let owner = ctx.accounts.owner.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[item.as_ref(), owner.as_ref(), STAKE_SEED, &[bump]]];
+
+        let delegate_accounts = TokenDelegateAccounts {
+            owner: ctx.accounts.owner.to_account_info(),
+            delegate: ctx.accounts.stake_record.to_account_info(),
+            payer: ctx.accounts.owner.to_account_info(),
+            token: ctx.accounts.item_token.to_account_info(),
+            mint: ctx.accounts.item.to_account_info(),
+            metadata: ctx.accounts.item_metadata.to_account_info(),
+            edition: ctx.accounts.edition.to_account_info(),
+            token_record: ctx.accounts.item_token_record.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            instructions: ctx.accounts.instructions.to_account_info(),
+            authorization_rules_program: ctx.accounts.authorization_rules_program.to_account_info(),
+            rules_acc: rules_acc.cloned(),
+        };
+        delegate_token(
+            &delegate_accounts,
+            DelegateArgs::StakingV1 {
+                amount: 1,
+                authorization_data: authorization_data.map(AuthorizationData::from),
+            },
+        )?;
+        lock_token(&delegate_accounts, signer_seeds)?;
 
+        let stake_record = &mut ctx.accounts.stake_record;
+        stake_record.bump = bump;
+        stake_record.item = item;
+        stake_record.item_token = ctx.accounts.item_token.key();
+        stake_record.owner = owner;
+        stake_record.staked_at = Clock::get()?.unix_timestamp;
+        stake_record.claimed_until = stake_record.staked_at;
 
-        let auth_data = Some(AuthorizationData {
-            payload
+        emit!(Staked {
+            stake_record: stake_record.key(),
+            mint: item,
+            owner,
+            staked_at: stake_record.staked_at,
         });
 
-        msg!("auth data: {:?}", auth_data);
+        Ok(())
+    }
 
-        let transfer_args = TransferArgs::V1 {
-            amount: 1,
-            authorization_data: auth_data
-            // authorization_data: None,
+    // unlocks the item and revokes our Staking delegate, the item stays where it was all along
+    pub fn unstake_pnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnstakePNFT<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
         };
 
-        msg!("setting up builder");
-        let mut builder = TransferBuilder::new();
-        builder
-            .authority(ctx.accounts.listing.key())
-            .token_owner(ctx.accounts.listing.key())
-            .token(ctx.accounts.listing_item_token.key())
-            .destination_owner(ctx.accounts.buyer.key())
-            .destination(ctx.accounts.buyer_item_token.key())
-            .mint(ctx.accounts.item.key())
-            .metadata(ctx.accounts.item_metadata.key())
-            .edition(ctx.accounts.edition.key())
-            .owner_token_record(ctx.accounts.listing_token_record.key())
-            .destination_token_record(ctx.accounts.buyer_token_record.key())
-            // .authorization_rules(ctx.accounts.ruleset.key())
-            .authorization_rules_program(ctx.accounts.authorization_rules_program.key())
-            .payer(ctx.accounts.buyer.key());
-
-        msg!("building transfer instruction");
-        let build_result = builder.build(transfer_args);
-
-        let instruction = match build_result {
-            Ok(transfer) => {
-                msg!("transfer instruction built");
-                transfer.instruction()
-            }
-            Err(err) => {
-                msg!("Error building transfer instruction: {:?}", err);
-                return Err(ErrorCode::TransferBuilderFailed.into());
-            }
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        let rules_acc = item_rules_acc(&metadata, auth_rules)?;
+
+        let stake_record = &ctx.accounts.stake_record;
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            stake_record.item.as_ref(),
+            stake_record.owner.as_ref(),
+            STAKE_SEED,
+            &[stake_record.bump],
+        ]];
+
+        let delegate_accounts = TokenDelegateAccounts {
+            owner: ctx.accounts.owner.to_account_info(),
+            delegate: stake_record.to_account_info(),
+            payer: ctx.accounts.owner.to_account_info(),
+            token: ctx.accounts.item_token.to_account_info(),
+            mint: ctx.accounts.item.to_account_info(),
+            metadata: ctx.accounts.item_metadata.to_account_info(),
+            edition: ctx.accounts.edition.to_account_info(),
+            token_record: ctx.accounts.item_token_record.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            instructions: ctx.accounts.instructions.to_account_info(),
+            authorization_rules_program: ctx.accounts.authorization_rules_program.to_account_info(),
+            rules_acc: rules_acc.cloned(),
         };
+        unlock_token(&delegate_accounts, signer_seeds)?;
+        revoke_token(&delegate_accounts, RevokeArgs::StakingV1)?;
 
-        let account_infos = [
-            ctx.accounts.listing.to_account_info(),
-            ctx.accounts.listing_item_token.to_account_info(),
-            ctx.accounts.buyer.to_account_info(),
-            ctx.accounts.buyer_item_token.to_account_info(),
-            ctx.accounts.item.to_account_info(),
-            ctx.accounts.item_metadata.to_account_info(),
-            ctx.accounts.edition.to_account_info(),
-            ctx.accounts.listing_token_record.to_account_info(),
-            ctx.accounts.buyer_token_record.to_account_info(),
-            ctx.accounts.ruleset.to_account_info(),
-            ctx.accounts.listing.to_account_info(),
-            ctx.accounts.token_metadata_program.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.instructions.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.associated_token_program.to_account_info(),
-            ctx.accounts.authorization_rules_program.to_account_info(),
-        ];
+        emit!(Unstaked {
+            stake_record: stake_record.key(),
+            mint: stake_record.item,
+            owner: stake_record.owner,
+            staked_at: stake_record.staked_at,
+            unstaked_at: Clock::get()?.unix_timestamp,
+        });
 
-        msg!("invoking transfer instruction");
+        // stake_record is closed to the owner by its constraint
+        Ok(())
+    }
 
-        let signer_seeds = &[listing.item.as_ref(), b"listings".as_ref(), &[listing.bump]];
+    // vault is the pool's ATA, fund it with plain token transfers. Or make the pool PDA the reward
+    // mint's authority and claims mint instead
+    pub fn init_reward_pool(
+        ctx: Context<InitRewardPool>,
+        rate_per_second: u64,
+        multipliers: Vec<CollectionMultiplier>,
+    ) -> Result<()> {
+        require!(
+            multipliers.len() <= MAX_COLLECTION_MULTIPLIERS,
+            ErrorCode::TooManyMultipliers
+        );
+        require!(
+            multipliers.iter().all(|m| m.collection != Pubkey::default()),
+            ErrorCode::InvalidMultiplier
+        );
 
-        invoke_signed(&instruction, &account_infos, &[signer_seeds]).unwrap();
+        let reward_pool = &mut ctx.accounts.reward_pool;
+        reward_pool.bump = *ctx.bumps.get("reward_pool").unwrap();
+        reward_pool.authority = ctx.accounts.authority.key();
+        reward_pool.reward_mint = ctx.accounts.reward_mint.key();
+        reward_pool.vault = ctx.accounts.vault.key();
+        reward_pool.rate_per_second = rate_per_second;
+        reward_pool.multipliers = multipliers;
 
         Ok(())
     }
 
-}
+    // applies to everything not claimed yet, not just from now on
+    pub fn update_reward_pool(
+        ctx: Context<UpdateRewardPool>,
+        rate_per_second: u64,
+        multipliers: Vec<CollectionMultiplier>,
+    ) -> Result<()> {
+        require!(
+            multipliers.len() <= MAX_COLLECTION_MULTIPLIERS,
+            ErrorCode::TooManyMultipliers
+        );
+        require!(
+            multipliers.iter().all(|m| m.collection != Pubkey::default()),
+            ErrorCode::InvalidMultiplier
+        );
 
-#[derive(Accounts)]
-pub struct BuyPNFT<'info> {
+        let reward_pool = &mut ctx.accounts.reward_pool;
+        reward_pool.rate_per_second = rate_per_second;
+        reward_pool.multipliers = multipliers;
 
-    pub item: Box<Account<'info, Mint>>,
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        token::mint = item,
-        token::authority = buyer
-    )]
-    pub buyer_item_token: Box<Account<'info, TokenAccount>>,
+    // pays out what the item earned since staked_at / the last claim. A stake record earns from
+    // one pool only, whichever it first claims from. Claim before unstaking, the rest is lost.
+    // Only items in one of the pool's verified collections earn anything
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        let collection = verified_collection(&metadata);
 
-    #[account(
-        mut,
-        has_one = item,
-        constraint = listing.item == item.key() && listing.item_token == listing_item_token.key(),
-    )]
-    pub listing: Box<Account<'info, Listing>>,
+        let now = Clock::get()?.unix_timestamp;
+        let reward_pool = &ctx.accounts.reward_pool;
+        let multiplier_bps = reward_pool
+            .multiplier_bps(&collection)
+            .ok_or(ErrorCode::CollectionNotRewarded)?;
+        let stake_record = &ctx.accounts.stake_record;
+        let from = stake_record.claimed_until;
+        let amount = reward_pool
+            .accrued(multiplier_bps, from, now)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
 
-    #[account(
-        mut,
-        associated_token::mint = item,
-        associated_token::authority = listing
-    )]
-    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            reward_pool.authority.as_ref(),
+            reward_pool.reward_mint.as_ref(),
+            REWARD_POOL_SEED,
+            &[reward_pool.bump],
+        ]];
+        if amount > 0 {
+            if ctx.accounts.reward_mint.mint_authority == Some(reward_pool.key()).into() {
+                token::mint_to(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        MintTo {
+                            mint: ctx.accounts.reward_mint.to_account_info(),
+                            to: ctx.accounts.owner_reward_token.to_account_info(),
+                            authority: reward_pool.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    amount,
+                )?;
+            } else {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: ctx.accounts.vault.to_account_info(),
+                            to: ctx.accounts.owner_reward_token.to_account_info(),
+                            authority: reward_pool.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    amount,
+                )?;
+            }
+        }
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+        let stake_record = &mut ctx.accounts.stake_record;
+        stake_record.reward_pool = ctx.accounts.reward_pool.key();
+        stake_record.claimed_until = now;
 
-    // programs
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+        emit!(RewardsClaimed {
+            reward_pool: stake_record.reward_pool,
+            stake_record: stake_record.key(),
+            mint: stake_record.item,
+            owner: stake_record.owner,
+            amount,
+            from,
+            to: now,
+        });
 
-    // pnft shit
+        Ok(())
+    }
+
+    // lender escrows the principal (in lamports) in the loan PDA, any item from `collection` can take it
+    pub fn offer_loan(
+        ctx: Context<OfferLoan>,
+        nonce: u64,
+        collection: Pubkey,
+        principal: u64,
+        apr_bps: u32,
+        duration: i64,
+    ) -> Result<()> {
+        require!(collection != Pubkey::default(), ErrorCode::CollectionMismatch);
+        require!(principal > 0 && duration > 0, ErrorCode::InvalidLoanTerms);
+        assert_trading_allowed(&ctx.accounts.config, &ctx.accounts.collection_status, &collection)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.lender.to_account_info(),
+                    to: ctx.accounts.loan.to_account_info(),
+                },
+            ),
+            principal,
+        )?;
+
+        let loan = &mut ctx.accounts.loan;
+        loan.bump = *ctx.bumps.get("loan").unwrap();
+        loan.lender = ctx.accounts.lender.key();
+        loan.nonce = nonce;
+        loan.collection = collection;
+        loan.principal = principal;
+        loan.apr_bps = apr_bps;
+        loan.duration = duration;
+
+        emit!(LoanOffered {
+            loan: loan.key(),
+            lender: loan.lender,
+            collection,
+            principal,
+            apr_bps,
+            duration,
+        });
+
+        Ok(())
+    }
+
+    // only while nobody took it, principal + rent go back to the lender
+    pub fn cancel_loan_offer(ctx: Context<CancelLoanOffer>) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        emit!(LoanOfferCancelled {
+            loan: loan.key(),
+            lender: loan.lender,
+        });
+
+        // loan is closed to the lender by its constraint
+        Ok(())
+    }
+
+    // borrower escrows the item in the loan's ATA and gets the principal
+    pub fn take_loan<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakeLoan<'info>>,
+        authorization_data: Option<AuthorizationDataLocal>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        // a print or fungible from the collection isn't the collateral the lender priced in
+        assert_pnft_master_edition(&ctx.accounts.item, &metadata, &ctx.accounts.edition)?;
+        require!(
+            verified_collection(&metadata) == ctx.accounts.loan.collection,
+            ErrorCode::CollectionMismatch
+        );
+        assert_trading_allowed(
+            &ctx.accounts.config,
+            &ctx.accounts.collection_status,
+            &ctx.accounts.loan.collection,
+        )?;
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        send_pnft(
+            &ctx.accounts.borrower.to_account_info(),
+            None,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.borrower_item_token,
+            &ctx.accounts.loan_item_token,
+            &ctx.accounts.loan.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.borrower_token_record,
+            &ctx.accounts.loan_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            authorization_data,
+            1,
+            None,
+        )?;
+
+        // after the CPI, TM doesn't see the loan's lamports move
+        let principal = ctx.accounts.loan.principal;
+        transfer_lamports_from_pda(
+            &ctx.accounts.loan.to_account_info(),
+            &ctx.accounts.borrower.to_account_info(),
+            principal,
+        )?;
+
+        let loan = &mut ctx.accounts.loan;
+        loan.borrower = ctx.accounts.borrower.key();
+        loan.item = ctx.accounts.item.key();
+        loan.started_at = Clock::get()?.unix_timestamp;
+
+        emit!(LoanTaken {
+            loan: loan.key(),
+            lender: loan.lender,
+            borrower: loan.borrower,
+            mint: loan.item,
+            principal,
+            due_at: loan.due_at(),
+        });
+
+        Ok(())
+    }
+
+    // borrower pays principal + interest to the lender and gets the item back. Works until the
+    // lender forecloses, even past the deadline
+    pub fn repay_loan<'info>(
+        ctx: Context<'_, '_, '_, 'info, RepayLoan<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        let now = Clock::get()?.unix_timestamp;
+        let interest = loan
+            .interest_due(now)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let repaid = loan
+            .principal
+            .checked_add(interest)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.borrower.to_account_info(),
+                    to: ctx.accounts.lender.to_account_info(),
+                },
+            ),
+            repaid,
+        )?;
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        let auth_data = loan.source_seeds();
+        let nonce = loan.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            loan.lender.as_ref(),
+            nonce.as_ref(),
+            LOAN_SEED,
+            &[loan.bump],
+        ]];
+        send_pnft(
+            &loan.to_account_info(),
+            None,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.loan_item_token,
+            &ctx.accounts.borrower_item_token,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.loan_token_record,
+            &ctx.accounts.borrower_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        // borrower paid for the escrow
+        close_token_account(
+            &ctx.accounts.loan_item_token,
+            &ctx.accounts.borrower.to_account_info(),
+            &loan.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(LoanRepaid {
+            loan: loan.key(),
+            lender: loan.lender,
+            borrower: loan.borrower,
+            mint: loan.item,
+            principal: loan.principal,
+            interest,
+        });
+
+        // loan is closed to the lender by its constraint
+        Ok(())
+    }
+
+    // past the deadline the lender takes the item instead, through the same ruleset-aware transfer
+    pub fn foreclose_loan<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForecloseLoan<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        require!(
+            Clock::get()?.unix_timestamp > loan.due_at(),
+            ErrorCode::LoanNotOverdue
+        );
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        let auth_data = loan.source_seeds();
+        let nonce = loan.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            loan.lender.as_ref(),
+            nonce.as_ref(),
+            LOAN_SEED,
+            &[loan.bump],
+        ]];
+        send_pnft(
+            &loan.to_account_info(),
+            None,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.loan_item_token,
+            &ctx.accounts.lender_item_token,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.loan_token_record,
+            &ctx.accounts.lender_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.loan_item_token,
+            &ctx.accounts.borrower.to_account_info(),
+            &loan.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(LoanForeclosed {
+            loan: loan.key(),
+            lender: loan.lender,
+            borrower: loan.borrower,
+            mint: loan.item,
+        });
+
+        // loan is closed to the lender by its constraint
+        Ok(())
+    }
+
+    // owner escrows the item in the rental PDA's ATA until somebody rents it
+    pub fn list_for_rent<'info>(
+        ctx: Context<'_, '_, '_, 'info, ListForRent<'info>>,
+        daily_price: u64,
+        max_days: u16,
+        authorization_data: Option<AuthorizationDataLocal>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        require!(max_days > 0, ErrorCode::InvalidRentalDays);
+        // the LockedTransfer delegate rentals rely on only exists for pNFTs, and renters get the
+        // same 1/1 guarantee buyers do
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        assert_pnft_master_edition(&ctx.accounts.item, &metadata, &ctx.accounts.edition)?;
+        assert_trading_allowed(
+            &ctx.accounts.config,
+            &ctx.accounts.collection_status,
+            &verified_collection(&metadata),
+        )?;
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        send_pnft(
+            &ctx.accounts.owner.to_account_info(),
+            None,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.owner_item_token,
+            &ctx.accounts.rental_item_token,
+            &ctx.accounts.rental.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.owner_token_record,
+            &ctx.accounts.rental_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            authorization_data,
+            1,
+            None,
+        )?;
+
+        let rental = &mut ctx.accounts.rental;
+        rental.bump = *ctx.bumps.get("rental").unwrap();
+        rental.item = ctx.accounts.item.key();
+        rental.owner = ctx.accounts.owner.key();
+        rental.daily_price = daily_price;
+        rental.max_days = max_days;
+
+        emit!(ListedForRent {
+            rental: rental.key(),
+            mint: rental.item,
+            owner: rental.owner,
+            daily_price,
+            max_days,
+        });
+
+        Ok(())
+    }
+
+    // only while it isn't rented, the item goes back to the owner
+    pub fn cancel_rental<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelRental<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+
+        let rental = &ctx.accounts.rental;
+        let auth_data = rental.seeds_payload(PayloadKey::SourceSeeds);
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            rental.item.as_ref(),
+            rental.owner.as_ref(),
+            RENTAL_SEED,
+            &[rental.bump],
+        ]];
+        send_pnft(
+            &rental.to_account_info(),
+            None,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.rental_item_token,
+            &ctx.accounts.owner_item_token,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.rental_token_record,
+            &ctx.accounts.owner_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.rental_item_token,
+            &ctx.accounts.owner.to_account_info(),
+            &rental.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(RentalCancelled {
+            rental: rental.key(),
+            mint: rental.item,
+            owner: rental.owner,
+        });
+
+        // rental is closed to the owner by its constraint
+        Ok(())
+    }
+
+    // renter pays for `days` up front and gets the item, locked in their wallet with the rental
+    // PDA as its LockedTransfer delegate, locked to the owner
+    pub fn rent_pnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, RentPNFT<'info>>,
+        days: u16,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rental = &ctx.accounts.rental;
+        require!(
+            days > 0 && days <= rental.max_days,
+            ErrorCode::InvalidRentalDays
+        );
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        assert_trading_allowed(
+            &ctx.accounts.config,
+            &ctx.accounts.collection_status,
+            &verified_collection(&metadata),
+        )?;
+
+        let total_price = rental
+            .daily_price
+            .checked_mul(days as u64)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.renter.to_account_info(),
+                    to: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            total_price,
+        )?;
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        let rules_acc = item_rules_acc(&metadata, auth_rules)?;
+
+        let auth_data = rental.seeds_payload(PayloadKey::SourceSeeds);
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            rental.item.as_ref(),
+            rental.owner.as_ref(),
+            RENTAL_SEED,
+            &[rental.bump],
+        ]];
+        send_pnft(
+            &rental.to_account_info(),
+            None,
+            &ctx.accounts.renter.to_account_info(),
+            &ctx.accounts.rental_item_token,
+            &ctx.accounts.renter_item_token,
+            &ctx.accounts.renter.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.rental_token_record,
+            &ctx.accounts.renter_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        // owner paid for the escrow, it's not needed past this point
+        close_token_account(
+            &ctx.accounts.rental_item_token,
+            &ctx.accounts.owner.to_account_info(),
+            &rental.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        let delegate_accounts = TokenDelegateAccounts {
+            owner: ctx.accounts.renter.to_account_info(),
+            delegate: rental.to_account_info(),
+            payer: ctx.accounts.renter.to_account_info(),
+            token: ctx.accounts.renter_item_token.to_account_info(),
+            mint: ctx.accounts.item.to_account_info(),
+            metadata: ctx.accounts.item_metadata.to_account_info(),
+            edition: ctx.accounts.edition.to_account_info(),
+            token_record: ctx.accounts.renter_token_record.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            instructions: ctx.accounts.instructions.to_account_info(),
+            authorization_rules_program: ctx.accounts.authorization_rules_program.to_account_info(),
+            rules_acc: rules_acc.cloned(),
+        };
+        delegate_token(
+            &delegate_accounts,
+            DelegateArgs::LockedTransferV1 {
+                amount: 1,
+                locked_address: rental.owner,
+                authorization_data: None,
+            },
+        )?;
+        lock_token(&delegate_accounts, signer_seeds)?;
+
+        let rented_until = Clock::get()?
+            .unix_timestamp
+            .checked_add(days as i64 * SECONDS_PER_DAY)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let rental = &mut ctx.accounts.rental;
+        rental.renter = ctx.accounts.renter.key();
+        rental.rented_until = rented_until;
+
+        emit!(Rented {
+            rental: rental.key(),
+            mint: rental.item,
+            owner: rental.owner,
+            renter: rental.renter,
+            days,
+            price: total_price,
+            rented_until,
+        });
+
+        Ok(())
+    }
+
+    // once the rental is over anyone can send the item back to the owner, payer covers the
+    // owner's ATA / token record if they closed theirs
+    pub fn end_rental<'info>(
+        ctx: Context<'_, '_, '_, 'info, EndRental<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rental = &ctx.accounts.rental;
+        require!(
+            Clock::get()?.unix_timestamp >= rental.rented_until,
+            ErrorCode::RentalNotExpired
+        );
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        let metadata =
+            assert_decode_metadata(&ctx.accounts.item, &ctx.accounts.item_metadata.to_account_info())?;
+        let rules_acc = item_rules_acc(&metadata, auth_rules)?;
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            rental.item.as_ref(),
+            rental.owner.as_ref(),
+            RENTAL_SEED,
+            &[rental.bump],
+        ]];
+        let delegate_accounts = TokenDelegateAccounts {
+            owner: ctx.accounts.renter.to_account_info(),
+            delegate: rental.to_account_info(),
+            payer: ctx.accounts.payer.to_account_info(),
+            token: ctx.accounts.renter_item_token.to_account_info(),
+            mint: ctx.accounts.item.to_account_info(),
+            metadata: ctx.accounts.item_metadata.to_account_info(),
+            edition: ctx.accounts.edition.to_account_info(),
+            token_record: ctx.accounts.renter_token_record.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            instructions: ctx.accounts.instructions.to_account_info(),
+            authorization_rules_program: ctx.accounts.authorization_rules_program.to_account_info(),
+            rules_acc: rules_acc.cloned(),
+        };
+        unlock_token(&delegate_accounts, signer_seeds)?;
+
+        // the delegate can only send it to the locked address, ie the owner. The transfer clears
+        // the delegate off the renter's token record too
+        send_pnft(
+            &ctx.accounts.renter.to_account_info(),
+            Some(&rental.to_account_info()),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.renter_item_token,
+            &ctx.accounts.owner_item_token,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.renter_token_record,
+            &ctx.accounts.owner_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(rental.seeds_payload(PayloadKey::AuthoritySeeds)),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        emit!(RentalEnded {
+            rental: rental.key(),
+            mint: rental.item,
+            owner: rental.owner,
+            renter: rental.renter,
+        });
+
+        // rental is closed to the owner by its constraint
+        Ok(())
+    }
+
+    // escrows several items under one bundle PDA, sold together for one price. Each item's
+    // accounts come in remaining accounts, see BUNDLE_ITEM_ACCOUNTS, followed by each item's
+    // collection status
+    pub fn list_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, ListBundle<'info>>,
+        nonce: u64,
+        price: u64,
+        authorization_data: Option<AuthorizationDataLocal>,
+    ) -> Result<()> {
+        let item_count = ctx.remaining_accounts.len() / (BUNDLE_ITEM_ACCOUNTS + 1);
+        require!(
+            item_count * (BUNDLE_ITEM_ACCOUNTS + 1) == ctx.remaining_accounts.len()
+                && (1..=MAX_BUNDLE_ITEMS).contains(&item_count),
+            ErrorCode::InvalidBundleSize
+        );
+        let (item_accounts, collection_statuses) =
+            ctx.remaining_accounts.split_at(item_count * BUNDLE_ITEM_ACCOUNTS);
+
+        let mut items = Vec::with_capacity(item_count);
+        for (item_accounts, collection_status) in item_accounts
+            .chunks(BUNDLE_ITEM_ACCOUNTS)
+            .zip(collection_statuses)
+        {
+            let item = item_accounts[0].key();
+            require!(!items.contains(&item), ErrorCode::BundleItemMismatch);
+            assert_bundle_item_listable(item_accounts, &ctx.accounts.config, collection_status)?;
+            send_bundle_item(
+                item_accounts,
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.bundle.to_account_info(),
+                &ctx.accounts.token_metadata_program,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+                &ctx.accounts.instructions,
+                &ctx.accounts.authorization_rules_program,
+                authorization_data.clone(),
+                None,
+            )?;
+            items.push(item);
+        }
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.bump = *ctx.bumps.get("bundle").unwrap();
+        bundle.seller = ctx.accounts.seller.key();
+        bundle.nonce = nonce;
+        bundle.price = price;
+        bundle.items = items;
+
+        emit!(BundleListed {
+            bundle: bundle.key(),
+            seller: bundle.seller,
+            nonce,
+            price,
+            items: bundle.items.clone(),
+        });
+
+        Ok(())
+    }
+
+    // all or nothing, every item goes to the buyer and the escrows + bundle close to the seller.
+    // Same remaining accounts as list_bundle
+    pub fn buy_bundle<'info>(ctx: Context<'_, '_, '_, 'info, BuyBundle<'info>>) -> Result<()> {
+        let bundle = &ctx.accounts.bundle;
+        require!(
+            ctx.remaining_accounts.len() == bundle.items.len() * (BUNDLE_ITEM_ACCOUNTS + 1),
+            ErrorCode::InvalidBundleSize
+        );
+        let (item_accounts, collection_statuses) = ctx
+            .remaining_accounts
+            .split_at(bundle.items.len() * BUNDLE_ITEM_ACCOUNTS);
+        for (item_accounts, collection_status) in item_accounts
+            .chunks(BUNDLE_ITEM_ACCOUNTS)
+            .zip(collection_statuses)
+        {
+            assert_bundle_item_trading_allowed(item_accounts, &ctx.accounts.config, collection_status)?;
+        }
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: ctx.accounts.seller.to_account_info(),
+                },
+            ),
+            bundle.price,
+        )?;
+
+        let nonce = bundle.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            bundle.seller.as_ref(),
+            nonce.as_ref(),
+            BUNDLE_SEED,
+            &[bundle.bump],
+        ]];
+        for (item, item_accounts) in bundle
+            .items
+            .iter()
+            .zip(item_accounts.chunks(BUNDLE_ITEM_ACCOUNTS))
+        {
+            require_keys_eq!(item_accounts[0].key(), *item, ErrorCode::BundleItemMismatch);
+            let escrow = send_bundle_item(
+                item_accounts,
+                &bundle.to_account_info(),
+                &ctx.accounts.buyer.to_account_info(),
+                &ctx.accounts.buyer.to_account_info(),
+                &ctx.accounts.token_metadata_program,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+                &ctx.accounts.instructions,
+                &ctx.accounts.authorization_rules_program,
+                Some(bundle.source_seeds()),
+                Some(signer_seeds),
+            )?;
+            close_token_account(
+                &escrow,
+                &ctx.accounts.seller.to_account_info(),
+                &bundle.to_account_info(),
+                &ctx.accounts.token_program,
+                signer_seeds,
+            )?;
+        }
+
+        emit!(BundleSold {
+            bundle: bundle.key(),
+            seller: bundle.seller,
+            buyer: ctx.accounts.buyer.key(),
+            price: bundle.price,
+            items: bundle.items.clone(),
+        });
+
+        // bundle is closed to the seller by its constraint
+        Ok(())
+    }
+
+    // returns every item to the seller, same item accounts as buy_bundle but no collection
+    // statuses, cancelling is always allowed
+    pub fn cancel_bundle<'info>(ctx: Context<'_, '_, '_, 'info, CancelBundle<'info>>) -> Result<()> {
+        let bundle = &ctx.accounts.bundle;
+        require!(
+            ctx.remaining_accounts.len() == bundle.items.len() * BUNDLE_ITEM_ACCOUNTS,
+            ErrorCode::InvalidBundleSize
+        );
+
+        let nonce = bundle.nonce.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            bundle.seller.as_ref(),
+            nonce.as_ref(),
+            BUNDLE_SEED,
+            &[bundle.bump],
+        ]];
+        for (item, item_accounts) in bundle
+            .items
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(BUNDLE_ITEM_ACCOUNTS))
+        {
+            require_keys_eq!(item_accounts[0].key(), *item, ErrorCode::BundleItemMismatch);
+            let escrow = send_bundle_item(
+                item_accounts,
+                &bundle.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.token_metadata_program,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+                &ctx.accounts.instructions,
+                &ctx.accounts.authorization_rules_program,
+                Some(bundle.source_seeds()),
+                Some(signer_seeds),
+            )?;
+            close_token_account(
+                &escrow,
+                &ctx.accounts.seller.to_account_info(),
+                &bundle.to_account_info(),
+                &ctx.accounts.token_program,
+                signer_seeds,
+            )?;
+        }
+
+        emit!(BundleCancelled {
+            bundle: bundle.key(),
+            seller: bundle.seller,
+            items: bundle.items.clone(),
+        });
+
+        // bundle is closed to the seller by its constraint
+        Ok(())
+    }
+
+    // one-off, the upgrade authority hands the marketplace controls to `authority`
+    pub fn init_config(ctx: Context<InitConfig>, authority: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.bump = *ctx.bumps.get("config").unwrap();
+        config.authority = authority;
+        Ok(())
+    }
+
+    // halts listing, buying and loan offers across the board
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.paused = paused;
+
+        emit!(PauseToggled {
+            config: config.key(),
+            authority: config.authority,
+            paused,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_collection_status(
+        ctx: Context<SetCollectionStatus>,
+        collection: Pubkey,
+        status: CollectionState,
+    ) -> Result<()> {
+        let collection_status = &mut ctx.accounts.collection_status;
+        collection_status.bump = *ctx.bumps.get("collection_status").unwrap();
+        collection_status.collection = collection;
+        collection_status.status = status;
+
+        emit!(CollectionStatusSet {
+            collection_status: collection_status.key(),
+            collection,
+            authority: ctx.accounts.authority.key(),
+            status,
+        });
+
+        Ok(())
+    }
+
+    // approvers can't be changed later, a 0 threshold leaves withdrawals to the owner alone
+    pub fn init_vault(
+        ctx: Context<InitVault>,
+        approvers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            approvers.len() <= MAX_VAULT_APPROVERS && threshold as usize <= approvers.len(),
+            ErrorCode::InvalidThreshold
+        );
+        // approvals are counted once per key, so duplicates could make the threshold unreachable
+        for (i, approver) in approvers.iter().enumerate() {
+            require!(!approvers[..i].contains(approver), ErrorCode::InvalidThreshold);
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.bump = *ctx.bumps.get("vault").unwrap();
+        vault.owner = ctx.accounts.owner.key();
+        vault.approvers = approvers;
+        vault.threshold = threshold;
+
+        emit!(VaultCreated {
+            vault: vault.key(),
+            owner: vault.owner,
+            approvers: vault.approvers.clone(),
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    // anyone can deposit into a vault, rulesets get the vault's seeds as DestinationSeeds
+    pub fn deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+
+        let vault = &ctx.accounts.vault;
+        send_pnft(
+            &ctx.accounts.depositor.to_account_info(),
+            None,
+            &ctx.accounts.depositor.to_account_info(),
+            &ctx.accounts.depositor_item_token,
+            &ctx.accounts.vault_item_token,
+            &vault.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.depositor_token_record,
+            &ctx.accounts.vault_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(vault.seeds_payload(PayloadKey::DestinationSeeds)),
+            1,
+            None,
+        )?;
+
+        emit!(VaultDeposited {
+            vault: vault.key(),
+            mint: ctx.accounts.item.key(),
+            owner: vault.owner,
+            depositor: ctx.accounts.depositor.key(),
+        });
+
+        Ok(())
+    }
+
+    // withdraw_to the owner's own wallet
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.receiver.key(),
+            ctx.accounts.owner.key(),
+            ErrorCode::BadReceiver
+        );
+        withdraw_to(ctx, rules_acc_present)
+    }
+
+    // owner signs, plus `threshold` of the vault's approvers (passed after the ruleset in
+    // remaining accounts). The vault's ATA is closed to the owner afterwards, even when someone
+    // else deposited the item and paid for it: the vault doesn't record who funded each ATA
+    pub fn withdraw_to<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+        let vault = &ctx.accounts.vault;
+        assert_vault_approvals(vault, rem_acc.as_slice())?;
+
+        let auth_data = vault.seeds_payload(PayloadKey::SourceSeeds);
+        let signer_seeds: &[&[&[u8]]] = &[&[vault.owner.as_ref(), VAULT_SEED, &[vault.bump]]];
+        send_pnft(
+            &vault.to_account_info(),
+            None,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.vault_item_token,
+            &ctx.accounts.receiver_item_token,
+            &ctx.accounts.receiver.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.vault_token_record,
+            &ctx.accounts.receiver_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.vault_item_token,
+            &ctx.accounts.owner.to_account_info(),
+            &vault.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(VaultWithdrawn {
+            vault: vault.key(),
+            mint: ctx.accounts.item.key(),
+            owner: vault.owner,
+            receiver: ctx.accounts.receiver.key(),
+        });
+
+        Ok(())
+    }
+
+    // also makes the multisig's vault, items deposited there only come out through
+    // propose_withdrawal -> approve -> execute_withdrawal
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        nonce: u64,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        timelock: i64,
+    ) -> Result<()> {
+        require!(
+            signers.len() <= MAX_MULTISIG_SIGNERS
+                && threshold > 0
+                && threshold as usize <= signers.len(),
+            ErrorCode::InvalidThreshold
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(!signers[..i].contains(signer), ErrorCode::InvalidThreshold);
+        }
+        require!(timelock >= 0, ErrorCode::InvalidTimelock);
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.bump = *ctx.bumps.get("multisig").unwrap();
+        multisig.creator = ctx.accounts.creator.key();
+        multisig.nonce = nonce;
+        multisig.signers = signers;
+        multisig.threshold = threshold;
+        multisig.timelock = timelock;
+
+        // a PDA can't sign withdraw, so only execute_withdrawal can move its items
+        let vault = &mut ctx.accounts.vault;
+        vault.bump = *ctx.bumps.get("vault").unwrap();
+        vault.owner = multisig.key();
+
+        emit!(MultisigCreated {
+            multisig: multisig.key(),
+            vault: vault.key(),
+            signers: multisig.signers.clone(),
+            threshold,
+            timelock,
+        });
+
+        Ok(())
+    }
+
+    // doesn't count as an approval, the proposer approves like everyone else
+    pub fn propose_withdrawal(
+        ctx: Context<ProposeWithdrawal>,
+        item: Pubkey,
+        receiver: Pubkey,
+    ) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.bump = *ctx.bumps.get("proposal").unwrap();
+        proposal.multisig = multisig.key();
+        proposal.index = multisig.proposal_count;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.item = item;
+        proposal.receiver = receiver;
+        proposal.created_at = Clock::get()?.unix_timestamp;
+        multisig.proposal_count += 1;
+
+        emit!(WithdrawalProposed {
+            proposal: proposal.key(),
+            multisig: multisig.key(),
+            proposer: proposal.proposer,
+            mint: item,
+            receiver,
+        });
+
+        Ok(())
+    }
+
+    // the timelock starts once the threshold is met
+    pub fn approve(ctx: Context<Approve>) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let approver = ctx.accounts.approver.key();
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            !proposal.approvals.contains(&approver),
+            ErrorCode::AlreadyApproved
+        );
+        proposal.approvals.push(approver);
+        if proposal.approvals.len() == multisig.threshold as usize {
+            proposal.approved_at = Clock::get()?.unix_timestamp;
+        }
+
+        emit!(WithdrawalApproved {
+            proposal: proposal.key(),
+            multisig: multisig.key(),
+            approver,
+            approvals: proposal.approvals.len() as u8,
+            executable_at: proposal.executable_at(multisig.timelock),
+        });
+
+        Ok(())
+    }
+
+    // anyone can execute once approved and the timelock is up, payer covers the receiver's
+    // ATA / token record. The vault's ATA and the proposal are closed to the proposer
+    pub fn execute_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteWithdrawal<'info>>,
+        rules_acc_present: bool,
+    ) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.approvals.len() >= multisig.threshold as usize,
+            ErrorCode::NotEnoughApprovals
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= proposal.executable_at(multisig.timelock),
+            ErrorCode::TimelockActive
+        );
+
+        let rem_acc = &mut ctx.remaining_accounts.iter();
+        let auth_rules = if rules_acc_present {
+            Some(next_account_info(rem_acc)?)
+        } else {
+            None
+        };
+
+        let vault = &ctx.accounts.vault;
+        let auth_data = vault.seeds_payload(PayloadKey::SourceSeeds);
+        let signer_seeds: &[&[&[u8]]] = &[&[vault.owner.as_ref(), VAULT_SEED, &[vault.bump]]];
+        send_pnft(
+            &vault.to_account_info(),
+            None,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.vault_item_token,
+            &ctx.accounts.receiver_item_token,
+            &ctx.accounts.receiver.to_account_info(),
+            &ctx.accounts.item,
+            &ctx.accounts.item_metadata,
+            &ctx.accounts.edition,
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.instructions,
+            &ctx.accounts.vault_token_record,
+            &ctx.accounts.receiver_token_record,
+            &ctx.accounts.authorization_rules_program,
+            auth_rules,
+            Some(auth_data),
+            1,
+            Some(signer_seeds),
+        )?;
+
+        close_token_account(
+            &ctx.accounts.vault_item_token,
+            &ctx.accounts.proposer.to_account_info(),
+            &vault.to_account_info(),
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+
+        emit!(WithdrawalExecuted {
+            proposal: proposal.key(),
+            multisig: multisig.key(),
+            mint: proposal.item,
+            receiver: proposal.receiver,
+        });
+
+        // proposal is closed to the proposer by its constraint
+        Ok(())
+    }
+
+}
+
+#[derive(Accounts)]
+pub struct BuyPNFT<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = buyer
+    )]
+    pub buyer_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = seller,
+        constraint = listing.item == item.key() && listing.item_token == listing_item_token.key(),
+        constraint = listing.to_account_info().data_len() == Listing::SIZE @ ErrorCode::ListingNotMigrated,
+        close = seller,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    /// CHECK: has_one on listing
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = listing
+    )]
+    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &buyer_item_token.key()).0)]
+    pub buyer_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &listing_item_token.key()).0)]
+    pub listing_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+
+    /// CHECK:
+    #[account()]
+    pub ruleset: UncheckedAccount<'info>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(price: u64, nonce: u64)]
+pub struct ListPNFT<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = authority
+    )]
+    pub authority_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            item.key().as_ref(),
+            authority.key().as_ref(),
+            nonce.to_le_bytes().as_ref(),
+            LISTING_SEED,
+        ],
+        bump,
+        space = Listing::SIZE,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = item,
+        associated_token::authority = listing
+    )]
+    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: assert_pnft_master_edition + address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &authority_item_token.key()).0)]
+    pub authority_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &listing_item_token.key()).0)]
+    pub listing_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+}
+
+
+
+#[derive(Accounts)]
+pub struct UpdatePrice<'info> {
+    #[account(
+        mut,
+        has_one = seller,
+        constraint = listing.to_account_info().data_len() == Listing::SIZE @ ErrorCode::ListingNotMigrated,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub seller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = seller,
+        constraint = listing.item_token == listing_item_token.key(),
+        close = seller,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = listing
+    )]
+    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = item,
+        associated_token::authority = seller
+    )]
+    pub seller_item_token: Box<Account<'info, TokenAccount>>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &listing_item_token.key()).0)]
+    pub listing_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &seller_item_token.key()).0)]
+    pub seller_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct MigrateListing<'info> {
+    /// CHECK: can't be deserialized until migrated, discriminator + seeds checked in the ix
+    #[account(mut, owner = crate::ID)]
+    pub listing: UncheckedAccount<'info>,
+
+    pub item: Box<Account<'info, Mint>>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnwindLegacyListing<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    // seeds depend on the layout, checked in the ix
+    #[account(
+        mut,
+        has_one = item,
+        constraint = legacy_listing.item_token == listing_item_token.key(),
+        close = seller,
+    )]
+    pub legacy_listing: Box<Account<'info, LegacyListing>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = legacy_listing
+    )]
+    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: checked against the recorded seller in the ix, baseline listings never recorded one
+    /// so there it's attested by the upgrade authority
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = item,
+        associated_token::authority = seller
+    )]
+    pub seller_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, program::PnftTransfer>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()))]
+    pub program_data: Account<'info, ProgramData>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &listing_item_token.key()).0)]
+    pub listing_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &seller_item_token.key()).0)]
+    pub seller_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct StakePNFT<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = owner
+    )]
+    pub item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = owner,
+        seeds = [item.key().as_ref(), owner.key().as_ref(), STAKE_SEED],
+        bump,
+        space = StakeRecord::SIZE,
+    )]
+    pub stake_record: Box<Account<'info, StakeRecord>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &item_token.key()).0)]
+    pub item_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct UnstakePNFT<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(mut, address = stake_record.item_token)]
+    pub item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [item.key().as_ref(), owner.key().as_ref(), STAKE_SEED],
+        bump = stake_record.bump,
+        has_one = item,
+        has_one = owner,
+        close = owner,
+    )]
+    pub stake_record: Box<Account<'info, StakeRecord>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &item_token.key()).0)]
+    pub item_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct InitRewardPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        seeds = [authority.key().as_ref(), reward_mint.key().as_ref(), REWARD_POOL_SEED],
+        bump,
+        space = RewardPool::SIZE,
+    )]
+    pub reward_pool: Box<Account<'info, RewardPool>>,
+
+    pub reward_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = reward_pool
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRewardPool<'info> {
+    #[account(mut, has_one = authority)]
+    pub reward_pool: Box<Account<'info, RewardPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = owner,
+        constraint = stake_record.reward_pool == Pubkey::default()
+            || stake_record.reward_pool == reward_pool.key() @ ErrorCode::WrongRewardPool,
+    )]
+    pub stake_record: Box<Account<'info, StakeRecord>>,
+
+    #[account(has_one = reward_mint, has_one = vault)]
+    pub reward_pool: Box<Account<'info, RewardPool>>,
+
+    #[account(mut)]
+    pub reward_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = reward_mint,
+        associated_token::authority = owner
+    )]
+    pub owner_reward_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct OfferLoan<'info> {
+    #[account(
+        init,
+        payer = lender,
+        seeds = [lender.key().as_ref(), nonce.to_le_bytes().as_ref(), LOAN_SEED],
+        bump,
+        space = Loan::SIZE,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelLoanOffer<'info> {
+    #[account(
+        mut,
+        has_one = lender,
+        constraint = loan.borrower == Pubkey::default() @ ErrorCode::LoanAlreadyTaken,
+        close = lender,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TakeLoan<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = borrower
+    )]
+    pub borrower_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = loan.borrower == Pubkey::default() @ ErrorCode::LoanAlreadyTaken,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(
+        init,
+        payer = borrower,
+        associated_token::mint = item,
+        associated_token::authority = loan
+    )]
+    pub loan_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &borrower_item_token.key()).0)]
+    pub borrower_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &loan_item_token.key()).0)]
+    pub loan_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct RepayLoan<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = lender,
+        has_one = borrower,
+        close = lender,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = loan
+    )]
+    pub loan_item_token: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: has_one on loan
+    #[account(mut)]
+    pub lender: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        associated_token::mint = item,
+        associated_token::authority = borrower
+    )]
+    pub borrower_item_token: Box<Account<'info, TokenAccount>>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &loan_item_token.key()).0)]
+    pub loan_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &borrower_item_token.key()).0)]
+    pub borrower_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct ForecloseLoan<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = lender,
+        has_one = borrower,
+        close = lender,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = loan
+    )]
+    pub loan_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: has_one on loan, gets the escrow's rent back
+    #[account(mut)]
+    pub borrower: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        associated_token::mint = item,
+        associated_token::authority = lender
+    )]
+    pub lender_item_token: Box<Account<'info, TokenAccount>>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &loan_item_token.key()).0)]
+    pub loan_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &lender_item_token.key()).0)]
+    pub lender_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct ListForRent<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = owner
+    )]
+    pub owner_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = owner,
+        seeds = [item.key().as_ref(), owner.key().as_ref(), RENTAL_SEED],
+        bump,
+        space = Rental::SIZE,
+    )]
+    pub rental: Box<Account<'info, Rental>>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = item,
+        associated_token::authority = rental
+    )]
+    pub rental_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &owner_item_token.key()).0)]
+    pub owner_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &rental_item_token.key()).0)]
+    pub rental_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct CancelRental<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = owner,
+        constraint = rental.renter == Pubkey::default() @ ErrorCode::AlreadyRented,
+        close = owner,
+    )]
+    pub rental: Box<Account<'info, Rental>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = rental
+    )]
+    pub rental_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = item,
+        associated_token::authority = owner
+    )]
+    pub owner_item_token: Box<Account<'info, TokenAccount>>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &rental_item_token.key()).0)]
+    pub rental_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &owner_item_token.key()).0)]
+    pub owner_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct RentPNFT<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = owner,
+        constraint = rental.renter == Pubkey::default() @ ErrorCode::AlreadyRented,
+    )]
+    pub rental: Box<Account<'info, Rental>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = rental
+    )]
+    pub rental_item_token: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: has_one on rental
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub renter: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        associated_token::mint = item,
+        associated_token::authority = renter
+    )]
+    pub renter_item_token: Box<Account<'info, TokenAccount>>,
+
+    // marketplace controls, see assert_trading_allowed
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: assert_trading_allowed, depends on the item's collection
+    pub collection_status: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &rental_item_token.key()).0)]
+    pub rental_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &renter_item_token.key()).0)]
+    pub renter_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+pub struct EndRental<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        has_one = item,
+        has_one = owner,
+        has_one = renter,
+        constraint = rental.renter != Pubkey::default() @ ErrorCode::NotRented,
+        close = owner,
+    )]
+    pub rental: Box<Account<'info, Rental>>,
+
+    /// CHECK: has_one on rental
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: has_one on rental
+    pub renter: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = renter
+    )]
+    pub renter_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = item,
+        associated_token::authority = owner
+    )]
+    pub owner_item_token: Box<Account<'info, TokenAccount>>,
+
+    // anyone
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &renter_item_token.key()).0)]
+    pub renter_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &owner_item_token.key()).0)]
+    pub owner_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct ListBundle<'info> {
+
+    #[account(
+        init,
+        payer = seller,
+        seeds = [seller.key().as_ref(), nonce.to_le_bytes().as_ref(), BUNDLE_SEED],
+        bump,
+        space = Bundle::SIZE,
+    )]
+    pub bundle: Box<Account<'info, Bundle>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    // marketplace controls, see assert_trading_allowed. Items can be from different collections so
+    // their statuses come in remaining accounts
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts, BUNDLE_ITEM_ACCOUNTS per item (in the bundle's order):
+    // - mint, metadata, edition
+    // - seller ATA, bundle ATA
+    // - seller token record, bundle token record
+    // - rules account (or the auth rules program if the item has none)
+    // then one collection status per item, same order
+}
+
+#[derive(Accounts)]
+pub struct BuyBundle<'info> {
+
+    #[account(mut, has_one = seller, close = seller)]
+    pub bundle: Box<Account<'info, Bundle>>,
+
+    /// CHECK: has_one on bundle
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    // marketplace controls, see assert_trading_allowed. Items can be from different collections so
+    // their statuses come in remaining accounts
+    /// CHECK: address below, doesn't have to exist
+    #[account(address = pda::find_config().0)]
+    pub config: UncheckedAccount<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts, BUNDLE_ITEM_ACCOUNTS per item (in the bundle's order):
+    // - mint, metadata, edition
+    // - bundle ATA, buyer ATA
+    // - bundle token record, buyer token record
+    // - rules account (or the auth rules program if the item has none)
+    // then one collection status per item, same order
+}
+
+#[derive(Accounts)]
+pub struct CancelBundle<'info> {
+
+    #[account(mut, has_one = seller, close = seller)]
+    pub bundle: Box<Account<'info, Bundle>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts, BUNDLE_ITEM_ACCOUNTS per item (in the bundle's order):
+    // - mint, metadata, edition
+    // - bundle ATA, seller ATA
+    // - bundle token record, seller token record
+    // - rules account (or the auth rules program if the item has none)
+}
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [CONFIG_SEED],
+        bump,
+        space = MarketConfig::SIZE,
+    )]
+    pub config: Box<Account<'info, MarketConfig>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, program::PnftTransfer>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(payer.key()))]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, has_one = authority)]
+    pub config: Box<Account<'info, MarketConfig>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(collection: Pubkey)]
+pub struct SetCollectionStatus<'info> {
+    #[account(has_one = authority)]
+    pub config: Box<Account<'info, MarketConfig>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [collection.as_ref(), COLLECTION_STATUS_SEED],
+        bump,
+        space = CollectionStatus::SIZE,
+    )]
+    pub collection_status: Box<Account<'info, CollectionStatus>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitVault<'info> {
+    #[account(
+        init,
+        payer = owner,
+        seeds = [owner.key().as_ref(), VAULT_SEED],
+        bump,
+        space = Vault::SIZE,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = item,
+        token::authority = depositor
+    )]
+    pub depositor_item_token: Box<Account<'info, TokenAccount>>,
+
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = item,
+        associated_token::authority = vault
+    )]
+    pub vault_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &depositor_item_token.key()).0)]
+    pub depositor_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &vault_item_token.key()).0)]
+    pub vault_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+}
+
+// used by both withdraw and withdraw_to, withdraw checks receiver is the owner
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+
+    pub item: Box<Account<'info, Mint>>,
+
+    #[account(has_one = owner)]
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        associated_token::mint = item,
+        associated_token::authority = vault
+    )]
+    pub vault_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: any wallet, it only receives the item
+    pub receiver: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = item,
+        associated_token::authority = receiver
+    )]
+    pub receiver_item_token: Box<Account<'info, TokenAccount>>,
+
+    // programs
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // pnft shit
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
+    pub item_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
+    pub edition: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &vault_item_token.key()).0)]
+    pub vault_token_record: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &receiver_item_token.key()).0)]
+    pub receiver_token_record: UncheckedAccount<'info>,
+
+    //can't deserialize directly coz Anchor traits not implemented
+    /// CHECK: address below
+    #[account(address = mpl_token_metadata::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    //sysvar ixs don't deserialize in anchor
+    /// CHECK: address below
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: address below
+    #[account(address = mpl_token_auth_rules::id())]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
+    // - approvers signing, as many as the vault's threshold
+}
 
-    //can't deserialize directly coz Anchor traits not implemented
-    /// CHECK: assert_decode_metadata + seeds below
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CreateMultisig<'info> {
     #[account(
-        mut,
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            item.key().as_ref(),
-        ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
+        init,
+        payer = creator,
+        seeds = [creator.key().as_ref(), nonce.to_le_bytes().as_ref(), MULTISIG_SEED],
+        bump,
+        space = Multisig::SIZE,
     )]
-    pub item_metadata: UncheckedAccount<'info>,
+    pub multisig: Box<Account<'info, Multisig>>,
 
-    //note that MASTER EDITION and EDITION share the same seeds, and so it's valid to check them here
-    /// CHECK: seeds below
     #[account(
-    mut,
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            item.key().as_ref(),
-            mpl_token_metadata::state::EDITION.as_bytes(),
-        ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
+        init,
+        payer = creator,
+        seeds = [multisig.key().as_ref(), VAULT_SEED],
+        bump,
+        space = Vault::SIZE,
     )]
-    pub edition: UncheckedAccount<'info>,
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: seeds below
+#[derive(Accounts)]
+pub struct ProposeWithdrawal<'info> {
     #[account(
         mut,
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            item.key().as_ref(),
-            mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-            buyer_item_token.key().as_ref()
-        ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
+        constraint = multisig.signers.contains(&proposer.key()) @ ErrorCode::NotASigner,
     )]
-    pub buyer_token_record: UncheckedAccount<'info>,
+    pub multisig: Box<Account<'info, Multisig>>,
 
-    /// CHECK: seeds below
     #[account(
-        mut,
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            item.key().as_ref(),
-            mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-            listing_item_token.key().as_ref()
+        init,
+        payer = proposer,
+        seeds = [
+            multisig.key().as_ref(),
+            multisig.proposal_count.to_le_bytes().as_ref(),
+            PROPOSAL_SEED
         ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
+        bump,
+        space = Proposal::SIZE,
     )]
-    pub listing_token_record: UncheckedAccount<'info>,
+    pub proposal: Box<Account<'info, Proposal>>,
 
-    //can't deserialize directly coz Anchor traits not implemented
-    /// CHECK: address below
-    #[account(address = mpl_token_metadata::id())]
-    pub token_metadata_program: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
 
-    //sysvar ixs don't deserialize in anchor
-    /// CHECK: address below
-    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
-    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: address below
-    #[account(address = mpl_token_auth_rules::id())]
-    pub authorization_rules_program: UncheckedAccount<'info>,
+#[derive(Accounts)]
+pub struct Approve<'info> {
+    #[account(constraint = multisig.signers.contains(&approver.key()) @ ErrorCode::NotASigner)]
+    pub multisig: Box<Account<'info, Multisig>>,
 
-    /// CHECK:
-    #[account()]
-    pub ruleset: UncheckedAccount<'info>,
-}
+    #[account(mut, has_one = multisig)]
+    pub proposal: Box<Account<'info, Proposal>>,
 
+    pub approver: Signer<'info>,
+}
 
 #[derive(Accounts)]
-pub struct ListPNFT<'info> {
+pub struct ExecuteWithdrawal<'info> {
 
     pub item: Box<Account<'info, Mint>>,
 
+    pub multisig: Box<Account<'info, Multisig>>,
+
     #[account(
         mut,
-        token::mint = item,
-        token::authority = authority
+        has_one = multisig,
+        has_one = item,
+        has_one = proposer,
+        has_one = receiver,
+        close = proposer,
     )]
-    pub authority_item_token: Box<Account<'info, TokenAccount>>,
+    pub proposal: Box<Account<'info, Proposal>>,
 
     #[account(
-        init,
-        payer = authority,
-        seeds = [item.key().as_ref(), "listings".as_bytes().as_ref()],
-        bump,
-        space = 8 + 128,
+        seeds = [multisig.key().as_ref(), VAULT_SEED],
+        bump = vault.bump,
     )]
-    pub listing: Box<Account<'info, Listing>>,
+    pub vault: Box<Account<'info, Vault>>,
 
     #[account(
-        init,
-        payer = authority,
+        mut,
         associated_token::mint = item,
-        associated_token::authority = listing
+        associated_token::authority = vault
     )]
-    pub listing_item_token: Box<Account<'info, TokenAccount>>,
+    pub vault_item_token: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: has_one on the proposal, gets its rent and the vault ATA's back
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub proposer: UncheckedAccount<'info>,
+
+    /// CHECK: has_one on the proposal
+    pub receiver: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = item,
+        associated_token::authority = receiver
+    )]
+    pub receiver_item_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
     // programs
     pub token_program: Program<'info, Token>,
@@ -378,62 +3639,21 @@ pub struct ListPNFT<'info> {
     // pnft shit
 
     //can't deserialize directly coz Anchor traits not implemented
-    /// CHECK: assert_decode_metadata + seeds below
-    #[account(
-    mut,
-    seeds=[
-    mpl_token_metadata::state::PREFIX.as_bytes(),
-    mpl_token_metadata::id().as_ref(),
-    item.key().as_ref(),
-    ],
-    seeds::program = mpl_token_metadata::id(),
-    bump
-    )]
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&item.key()).0)]
     pub item_metadata: UncheckedAccount<'info>,
 
-    //note that MASTER EDITION and EDITION share the same seeds, and so it's valid to check them here
-    /// CHECK: seeds below
-    #[account(
-    seeds=[
-    mpl_token_metadata::state::PREFIX.as_bytes(),
-    mpl_token_metadata::id().as_ref(),
-    item.key().as_ref(),
-    mpl_token_metadata::state::EDITION.as_bytes(),
-    ],
-    seeds::program = mpl_token_metadata::id(),
-    bump
-    )]
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&item.key()).0)]
     pub edition: UncheckedAccount<'info>,
 
-    /// CHECK: seeds below
-    #[account(
-    mut,
-    seeds=[
-    mpl_token_metadata::state::PREFIX.as_bytes(),
-    mpl_token_metadata::id().as_ref(),
-    item.key().as_ref(),
-    mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-    authority_item_token.key().as_ref()
-    ],
-    seeds::program = mpl_token_metadata::id(),
-    bump
-    )]
-    pub authority_token_record: UncheckedAccount<'info>,
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &vault_item_token.key()).0)]
+    pub vault_token_record: UncheckedAccount<'info>,
 
-    /// CHECK: seeds below
-    #[account(
-    mut,
-    seeds=[
-    mpl_token_metadata::state::PREFIX.as_bytes(),
-    mpl_token_metadata::id().as_ref(),
-    item.key().as_ref(),
-    mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-    listing_item_token.key().as_ref()
-    ],
-    seeds::program = mpl_token_metadata::id(),
-    bump
-    )]
-    pub listing_token_record: UncheckedAccount<'info>,
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&item.key(), &receiver_item_token.key()).0)]
+    pub receiver_token_record: UncheckedAccount<'info>,
 
     //can't deserialize directly coz Anchor traits not implemented
     /// CHECK: address below
@@ -448,10 +3668,11 @@ pub struct ListPNFT<'info> {
     /// CHECK: address below
     #[account(address = mpl_token_auth_rules::id())]
     pub authorization_rules_program: UncheckedAccount<'info>,
+    //
+    // remaining accounts could be passed, in this order:
+    // - rules account
 }
 
-
-
 #[derive(Accounts)]
 pub struct TransferPNFT<'info> {
     #[account(mut)]
@@ -471,63 +3692,24 @@ pub struct TransferPNFT<'info> {
 
     // pfnt
     //can't deserialize directly coz Anchor traits not implemented
-    /// CHECK: assert_decode_metadata + seeds below
-    #[account(
-        mut,
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            nft_mint.key().as_ref(),
-        ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
-    )]
+    /// CHECK: assert_decode_metadata + address below
+    #[account(mut, address = pda::find_metadata(&nft_mint.key()).0)]
     pub nft_metadata: UncheckedAccount<'info>,
-    //note that MASTER EDITION and EDITION share the same seeds, and so it's valid to check them here
-    /// CHECK: seeds below
-    #[account(
-        seeds=[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            nft_mint.key().as_ref(),
-            mpl_token_metadata::state::EDITION.as_bytes(),
-        ],
-        seeds::program = mpl_token_metadata::id(),
-        bump
-    )]
+    /// CHECK: address below
+    #[account(address = pda::find_edition(&nft_mint.key()).0)]
     pub edition: UncheckedAccount<'info>,
-    /// CHECK: seeds below
-    #[account(mut,
-            seeds=[
-                mpl_token_metadata::state::PREFIX.as_bytes(),
-                mpl_token_metadata::id().as_ref(),
-                nft_mint.key().as_ref(),
-                mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-                src.key().as_ref()
-            ],
-            seeds::program = mpl_token_metadata::id(),
-            bump
-        )]
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&nft_mint.key(), &src.key()).0)]
     pub owner_token_record: UncheckedAccount<'info>,
-    /// CHECK: seeds below
-    #[account(mut,
-            seeds=[
-                mpl_token_metadata::state::PREFIX.as_bytes(),
-                mpl_token_metadata::id().as_ref(),
-                nft_mint.key().as_ref(),
-                mpl_token_metadata::state::TOKEN_RECORD_SEED.as_bytes(),
-                dest.key().as_ref()
-            ],
-            seeds::program = mpl_token_metadata::id(),
-            bump
-        )]
+    /// CHECK: address below
+    #[account(mut, address = pda::find_token_record(&nft_mint.key(), &dest.key()).0)]
     pub dest_token_record: UncheckedAccount<'info>,
     pub pnft_shared: ProgNftShared<'info>,
     //
     // remaining accounts could be passed, in this order:
     // - rules account
-    // - mint_whitelist_proof
-    // - creator_whitelist_proof
+    // - SPL Memo program, to also write the memo through it
+    // - payer, signing, to cover the dest token record rent instead of the owner
 }
 
 #[derive(Accounts)]
@@ -553,11 +3735,22 @@ pub struct ProgNftShared<'info> {
 pub struct AuthorizationDataLocal {
     pub payload: Vec<TaggedPayload>,
 }
+impl AuthorizationDataLocal {
+    // for rulesets that check a PDA's seeds, e.g. SourceSeeds when the item is owned by a PDA
+    pub fn seeds(key: PayloadKey, seeds: Vec<Vec<u8>>) -> Self {
+        Self {
+            payload: vec![TaggedPayload {
+                name: key.to_string(),
+                payload: PayloadTypeLocal::Seeds(SeedsVecLocal { seeds }),
+            }],
+        }
+    }
+}
 impl From<AuthorizationDataLocal> for AuthorizationData {
     fn from(val: AuthorizationDataLocal) -> Self {
         let mut p = Payload::new();
         val.payload.into_iter().for_each(|tp| {
-            p.insert(tp.name, PayloadType::try_from(tp.payload).unwrap());
+            p.insert(tp.name, PayloadType::from(tp.payload));
         });
         AuthorizationData { payload: p }
     }
@@ -569,8 +3762,8 @@ impl From<AuthorizationDataLocal> for AuthorizationData {
 // so have to create this stupid temp struct for IDL to serialize correctly
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct TaggedPayload {
-    name: String,
-    payload: PayloadTypeLocal,
+    pub name: String,
+    pub payload: PayloadTypeLocal,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
@@ -588,12 +3781,9 @@ impl From<PayloadTypeLocal> for PayloadType {
     fn from(val: PayloadTypeLocal) -> Self {
         match val {
             PayloadTypeLocal::Pubkey(pubkey) => PayloadType::Pubkey(pubkey),
-            PayloadTypeLocal::Seeds(seeds) => {
-                msg!(">>> seeds: {:?}", seeds);
-                PayloadType::Seeds(SeedsVec::try_from(seeds).unwrap())
-            }
+            PayloadTypeLocal::Seeds(seeds) => PayloadType::Seeds(SeedsVec::from(seeds)),
             PayloadTypeLocal::MerkleProof(proof) => {
-                PayloadType::MerkleProof(ProofInfo::try_from(proof).unwrap())
+                PayloadType::MerkleProof(ProofInfo::from(proof))
             }
             PayloadTypeLocal::Number(number) => PayloadType::Number(number),
         }
@@ -622,15 +3812,476 @@ impl From<ProofInfoLocal> for ProofInfo {
     }
 }
 
+// Listing field offsets, counting the 8 byte discriminator, for getProgramAccounts memcmp filters.
+// The fields below are all fixed size so these never move, new fields only get appended. The one
+// exception is version 2 dropping quantity, which moved version and reserved up 8 bytes
+pub const LISTING_BUMP_OFFSET: usize = 8;
+pub const LISTING_ITEM_OFFSET: usize = LISTING_BUMP_OFFSET + 1;
+pub const LISTING_ITEM_TOKEN_OFFSET: usize = LISTING_ITEM_OFFSET + 32;
+pub const LISTING_SELLER_OFFSET: usize = LISTING_ITEM_TOKEN_OFFSET + 32;
+pub const LISTING_COLLECTION_OFFSET: usize = LISTING_SELLER_OFFSET + 32;
+pub const LISTING_NONCE_OFFSET: usize = LISTING_COLLECTION_OFFSET + 32;
+pub const LISTING_PRICE_OFFSET: usize = LISTING_NONCE_OFFSET + 8;
+pub const LISTING_VERSION_OFFSET: usize = LISTING_PRICE_OFFSET + 8;
+pub const LISTING_RESERVED_OFFSET: usize = LISTING_VERSION_OFFSET + 1;
+
+pub const LISTING_VERSION: u8 = 2;
+pub const LISTING_RESERVED_SIZE: usize = 64;
+
 #[account]
 pub struct Listing {
 
     // these are for doing gPA lookups
     pub bump: u8,
-    // todo: add collection for lookups too
-    // pub collection: Pubkey,
+    pub item: Pubkey,
+    pub item_token: Pubkey,
+    pub seller: Pubkey,
+    // verified collection of the item, default pubkey if it has none
+    pub collection: Pubkey,
+
+    // lets the same seller have several listings of the same item
+    pub nonce: u64,
+
+    // in lamports, for everything in escrow. That's the one unit unless the listing was
+    // migrated from a bigger lot
+    pub price: u64,
+
+    // bumped whenever the layout changes, new fields come out of reserved
+    pub version: u8,
+    pub reserved: [u8; LISTING_RESERVED_SIZE],
+}
+
+impl Listing {
+    // discriminator included
+    pub const SIZE: usize = LISTING_RESERVED_OFFSET + LISTING_RESERVED_SIZE;
+}
+
+
+// what a Listing looked like before nonce was part of the seeds. The baseline ones, seeded by
+// [item, "listings"], stop after item_token so seller reads as the default pubkey. The next ones,
+// seeded by [item, seller, "listings"], went on with seller, price and quantity.
+// Both were written with Listing's discriminator, so can't be their own #[account]
+pub const LEGACY_LISTING_SIZE: usize = 8 + 128;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LegacyListing {
+    pub bump: u8,
+    pub item: Pubkey,
+    pub item_token: Pubkey,
+    pub seller: Pubkey,
+}
+
+impl LegacyListing {
+    // without the bump
+    pub fn seeds(&self) -> Vec<&[u8]> {
+        if self.seller == Pubkey::default() {
+            vec![self.item.as_ref(), LISTING_SEED]
+        } else {
+            vec![self.item.as_ref(), self.seller.as_ref(), LISTING_SEED]
+        }
+    }
+}
+
+impl AccountDeserialize for LegacyListing {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < 8 || buf[..8] != <Listing as Discriminator>::DISCRIMINATOR {
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data: &[u8] = &buf[8..];
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+// read-only, only ever closed
+impl AccountSerialize for LegacyListing {}
+
+impl Owner for LegacyListing {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+// the layouts migrate_listing brings up to the current one, all seeded by item + seller + nonce.
+// Version 1 (PRE_VERSION_LISTING_SIZE and LISTING_V1_SIZE) starts with these fields, the one
+// before it stopped here
+pub const PRE_VERSION_LISTING_SIZE: usize = 8 + 153;
+pub const LISTING_V1_SIZE: usize = PRE_VERSION_LISTING_SIZE + 1 + LISTING_RESERVED_SIZE;
+
+#[derive(AnchorDeserialize)]
+pub struct ListingV1 {
+    pub bump: u8,
+    pub item: Pubkey,
+    pub item_token: Pubkey,
+    pub seller: Pubkey,
+    pub collection: Pubkey,
+    pub nonce: u64,
+    // per unit
+    pub price: u64,
+    pub quantity: u64,
+}
+
+// before collection was inserted after seller, LEGACY_LISTING_SIZE like the legacy ones
+#[derive(AnchorDeserialize)]
+pub struct PreCollectionListing {
+    pub bump: u8,
+    pub item: Pubkey,
+    pub item_token: Pubkey,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+impl From<PreCollectionListing> for ListingV1 {
+    fn from(old: PreCollectionListing) -> Self {
+        ListingV1 {
+            bump: old.bump,
+            item: old.item,
+            item_token: old.item_token,
+            seller: old.seller,
+            collection: Pubkey::default(),
+            nonce: old.nonce,
+            price: old.price,
+            quantity: old.quantity,
+        }
+    }
+}
 
+pub const STAKE_RECORD_RESERVED_SIZE: usize = 24;
+
+// one per staked item, seeded by [item, owner, "stake"]. Closed on unstake
+#[account]
+pub struct StakeRecord {
+    pub bump: u8,
     pub item: Pubkey,
     pub item_token: Pubkey,
+    pub owner: Pubkey,
+    // unix timestamp
+    pub staked_at: i64,
+
+    // pool the item earns from, default pubkey until its first claim_rewards
+    pub reward_pool: Pubkey,
+    // rewards are paid out up to here, starts at staked_at
+    pub claimed_until: i64,
+
+    pub reserved: [u8; STAKE_RECORD_RESERVED_SIZE],
+}
+
+impl StakeRecord {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 * 3 + 8 + 32 + 8 + STAKE_RECORD_RESERVED_SIZE;
+}
+
+pub const MAX_COLLECTION_MULTIPLIERS: usize = 16;
+// 1x
+pub const BASE_MULTIPLIER_BPS: u16 = 10_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectionMultiplier {
+    // verified collection
+    pub collection: Pubkey,
+    // of rate_per_second, BASE_MULTIPLIER_BPS is 1x
+    pub multiplier_bps: u16,
+}
+
+// seeded by [authority, reward_mint, "reward_pool"], signs for the vault / mints rewards
+#[account]
+pub struct RewardPool {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub reward_mint: Pubkey,
+    // the pool's ATA of reward_mint, paid out of unless the pool is the mint authority
+    pub vault: Pubkey,
+    // per staked item per second, in reward_mint base units
+    pub rate_per_second: u64,
+    // the collections whose items can claim, anything else (unverified included) earns nothing
+    pub multipliers: Vec<CollectionMultiplier>,
+}
+
+impl RewardPool {
+    // discriminator included, multipliers at their max length
+    pub const SIZE: usize = 8 + 1 + 32 * 3 + 8 + 4 + MAX_COLLECTION_MULTIPLIERS * (32 + 2);
+
+    // None when the pool doesn't reward `collection`
+    pub fn multiplier_bps(&self, collection: &Pubkey) -> Option<u16> {
+        if *collection == Pubkey::default() {
+            return None;
+        }
+        self.multipliers
+            .iter()
+            .find(|m| m.collection == *collection)
+            .map(|m| m.multiplier_bps)
+    }
+
+    // what an item at `multiplier_bps` earned between the two timestamps, None on overflow
+    pub fn accrued(&self, multiplier_bps: u16, from: i64, to: i64) -> Option<u64> {
+        let elapsed = u128::try_from(to.saturating_sub(from).max(0)).ok()?;
+        let amount = elapsed
+            .checked_mul(self.rate_per_second as u128)?
+            .checked_mul(multiplier_bps as u128)?
+            / BASE_MULTIPLIER_BPS as u128;
+        u64::try_from(amount).ok()
+    }
+}
+
+pub const LOAN_RESERVED_SIZE: usize = 64;
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+// seeded by [lender, nonce, "loans"]. Holds the principal while it's an offer, then the item
+// (in its ATA) while the loan runs. Closed to the lender on repay / foreclose
+#[account]
+pub struct Loan {
+    pub bump: u8,
+    pub lender: Pubkey,
+    // lets the same lender have several offers out
+    pub nonce: u64,
+    // verified collection the item has to be from
+    pub collection: Pubkey,
+    // in lamports
+    pub principal: u64,
+    // simple interest, accrued per second on the principal
+    pub apr_bps: u32,
+    // seconds from started_at until the lender can foreclose
+    pub duration: i64,
+
+    // default pubkey / 0 until a borrower takes the offer
+    pub borrower: Pubkey,
+    pub item: Pubkey,
+    pub started_at: i64,
+
+    pub reserved: [u8; LOAN_RESERVED_SIZE],
+}
+
+impl Loan {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 8 + 32 + 8 + 4 + 8 + 32 + 32 + 8 + LOAN_RESERVED_SIZE;
+
+    pub fn due_at(&self) -> i64 {
+        self.started_at.saturating_add(self.duration)
+    }
+
+    // interest owed if repaid at `now`, None on overflow
+    pub fn interest_due(&self, now: i64) -> Option<u64> {
+        let elapsed = u128::try_from(now.saturating_sub(self.started_at).max(0)).ok()?;
+        let interest = (self.principal as u128)
+            .checked_mul(self.apr_bps as u128)?
+            .checked_mul(elapsed)?
+            / (10_000 * SECONDS_PER_YEAR as u128);
+        u64::try_from(interest).ok()
+    }
+
+    // the loan PDA owns the escrowed item, so rulesets need its seeds to approve the transfer
+    pub fn source_seeds(&self) -> AuthorizationDataLocal {
+        AuthorizationDataLocal::seeds(
+            PayloadKey::SourceSeeds,
+            vec![
+                self.lender.as_ref().to_vec(),
+                self.nonce.to_le_bytes().to_vec(),
+                LOAN_SEED.to_vec(),
+            ],
+        )
+    }
+}
+
+pub const RENTAL_RESERVED_SIZE: usize = 64;
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// seeded by [item, owner, "rentals"]. Holds the item in its ATA until rented, then is the
+// item's LockedTransfer delegate in the renter's wallet. Closed to the owner when it ends
+#[account]
+pub struct Rental {
+    pub bump: u8,
+    pub item: Pubkey,
+    pub owner: Pubkey,
+    // in lamports
+    pub daily_price: u64,
+    pub max_days: u16,
+
+    // default pubkey / 0 until rented
+    pub renter: Pubkey,
+    pub rented_until: i64,
+
+    pub reserved: [u8; RENTAL_RESERVED_SIZE],
+}
+
+impl Rental {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 32 + 8 + 2 + 32 + 8 + RENTAL_RESERVED_SIZE;
+
+    // rulesets need the PDA's seeds to approve it moving the item, as the owner or as the delegate
+    pub fn seeds_payload(&self, key: PayloadKey) -> AuthorizationDataLocal {
+        AuthorizationDataLocal::seeds(
+            key,
+            vec![
+                self.item.as_ref().to_vec(),
+                self.owner.as_ref().to_vec(),
+                RENTAL_SEED.to_vec(),
+            ],
+        )
+    }
+}
+
+// ~200k CUs per pNFT transfer, keeps a full bundle under the 1.4M limit
+pub const MAX_BUNDLE_ITEMS: usize = 6;
+pub const BUNDLE_RESERVED_SIZE: usize = 64;
+
+// seeded by [seller, nonce, "bundles"]. Holds every item in its own ATA until the whole lot is
+// bought or cancelled
+#[account]
+pub struct Bundle {
+    pub bump: u8,
+    pub seller: Pubkey,
+    pub nonce: u64,
+    // for the whole lot, in lamports
+    pub price: u64,
+    // mints, in the order their remaining accounts have to be passed
+    pub items: Vec<Pubkey>,
+
+    pub reserved: [u8; BUNDLE_RESERVED_SIZE],
+}
+
+impl Bundle {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 8 + 8 + 4 + 32 * MAX_BUNDLE_ITEMS + BUNDLE_RESERVED_SIZE;
+
+    // the bundle PDA owns the escrowed items, so rulesets need its seeds to approve the transfers
+    pub fn source_seeds(&self) -> AuthorizationDataLocal {
+        AuthorizationDataLocal::seeds(
+            PayloadKey::SourceSeeds,
+            vec![
+                self.seller.as_ref().to_vec(),
+                self.nonce.to_le_bytes().to_vec(),
+                BUNDLE_SEED.to_vec(),
+            ],
+        )
+    }
+}
+
+pub const CONFIG_RESERVED_SIZE: usize = 64;
+
+// seeded by ["config"], made by the upgrade authority with init_config
+#[account]
+pub struct MarketConfig {
+    pub bump: u8,
+    // flips `paused` and collection statuses
+    pub authority: Pubkey,
+    pub paused: bool,
+
+    pub reserved: [u8; CONFIG_RESERVED_SIZE],
+}
+
+impl MarketConfig {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 1 + CONFIG_RESERVED_SIZE;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollectionState {
+    Allowed,
+    // no listing, buying or loans against it
+    Frozen,
+}
+
+// seeded by [collection, "collection_status"], collections without one are allowed
+#[account]
+pub struct CollectionStatus {
+    pub bump: u8,
+    pub collection: Pubkey,
+    pub status: CollectionState,
+}
+
+impl CollectionStatus {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 1;
+}
+
+// plenty for an order id / reference, keeps the event and the Memo CPI cheap
+pub const MAX_MEMO_LEN: usize = 128;
+
+pub const MAX_VAULT_APPROVERS: usize = 5;
+pub const VAULT_RESERVED_SIZE: usize = 64;
+
+// seeded by [owner, "vault"]. Holds deposited items in its ATAs, they only come out with the
+// owner's signature plus `threshold` of the approvers'
+#[account]
+pub struct Vault {
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub approvers: Vec<Pubkey>,
+    // 0 means the owner alone
+    pub threshold: u8,
+
+    pub reserved: [u8; VAULT_RESERVED_SIZE],
+}
+
+impl Vault {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 4 + 32 * MAX_VAULT_APPROVERS + 1 + VAULT_RESERVED_SIZE;
+
+    // DestinationSeeds going in, SourceSeeds coming out
+    pub fn seeds_payload(&self, key: PayloadKey) -> AuthorizationDataLocal {
+        AuthorizationDataLocal::seeds(
+            key,
+            vec![self.owner.as_ref().to_vec(), VAULT_SEED.to_vec()],
+        )
+    }
+}
+
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+pub const MULTISIG_RESERVED_SIZE: usize = 64;
+
+// seeded by [creator, nonce, "multisig"]. Owns the vault seeded [multisig, "vault"], withdrawals
+// from it need `threshold` approvals on a proposal and then `timelock` seconds
+#[account]
+pub struct Multisig {
+    pub bump: u8,
+    pub creator: Pubkey,
+    pub nonce: u64,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timelock: i64,
+    // next proposal's index
+    pub proposal_count: u64,
+
+    pub reserved: [u8; MULTISIG_RESERVED_SIZE],
+}
+
+impl Multisig {
+    // discriminator included
+    pub const SIZE: usize =
+        8 + 1 + 32 + 8 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1 + 8 + 8 + MULTISIG_RESERVED_SIZE;
+}
+
+// seeded by [multisig, index, "proposal"], closed to the proposer once executed
+#[account]
+pub struct Proposal {
+    pub bump: u8,
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    // what to send out of the multisig's vault, and to whom
+    pub item: Pubkey,
+    pub receiver: Pubkey,
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    // 0 until the threshold is met
+    pub approved_at: i64,
 }
 
+impl Proposal {
+    // discriminator included
+    pub const SIZE: usize = 8 + 1 + 32 + 8 + 32 + 32 + 32 + 4 + 32 * MAX_MULTISIG_SIGNERS + 8 + 8;
+
+    // 0 until the threshold is met
+    pub fn executable_at(&self, timelock: i64) -> i64 {
+        if self.approved_at == 0 {
+            return 0;
+        }
+        self.approved_at.saturating_add(timelock)
+    }
+}
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+// Every PDA this program reads or signs for. Used by the account constraints and by off-chain
+// callers (see pnft_transfer_client), so keep seeds here and nowhere else.
+
+pub const LISTING_SEED: &[u8] = b"listings";
+pub const STAKE_SEED: &[u8] = b"stake";
+pub const REWARD_POOL_SEED: &[u8] = b"reward_pool";
+pub const LOAN_SEED: &[u8] = b"loans";
+pub const RENTAL_SEED: &[u8] = b"rentals";
+pub const BUNDLE_SEED: &[u8] = b"bundles";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const COLLECTION_STATUS_SEED: &[u8] = b"collection_status";
+pub const VAULT_SEED: &[u8] = b"vault";
+pub const MULTISIG_SEED: &[u8] = b"multisig";
+pub const PROPOSAL_SEED: &[u8] = b"proposal";
+
+pub fn find_metadata(mint: &Pubkey) -> (Pubkey, u8) {
+    mpl_token_metadata::pda::find_metadata_account(mint)
+}
+
+//note that MASTER EDITION and EDITION share the same seeds
+pub fn find_edition(mint: &Pubkey) -> (Pubkey, u8) {
+    mpl_token_metadata::pda::find_master_edition_account(mint)
+}
+
+pub fn find_token_record(mint: &Pubkey, token: &Pubkey) -> (Pubkey, u8) {
+    mpl_token_metadata::pda::find_token_record_account(mint, token)
+}
+
+pub fn find_listing(mint: &Pubkey, seller: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            mint.as_ref(),
+            seller.as_ref(),
+            nonce.to_le_bytes().as_ref(),
+            LISTING_SEED,
+        ],
+        &crate::ID,
+    )
+}
+
+// also the Staking delegate of the item while it's staked
+pub fn find_stake_record(mint: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), owner.as_ref(), STAKE_SEED], &crate::ID)
+}
+
+pub fn find_reward_pool(authority: &Pubkey, reward_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[authority.as_ref(), reward_mint.as_ref(), REWARD_POOL_SEED],
+        &crate::ID,
+    )
+}
+
+pub fn find_loan(lender: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[lender.as_ref(), nonce.to_le_bytes().as_ref(), LOAN_SEED],
+        &crate::ID,
+    )
+}
+
+pub fn find_rental(mint: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), owner.as_ref(), RENTAL_SEED], &crate::ID)
+}
+
+pub fn find_bundle(seller: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[seller.as_ref(), nonce.to_le_bytes().as_ref(), BUNDLE_SEED],
+        &crate::ID,
+    )
+}
+
+pub fn find_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], &crate::ID)
+}
+
+pub fn find_collection_status(collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[collection.as_ref(), COLLECTION_STATUS_SEED], &crate::ID)
+}
+
+// owns (the ATAs of) everything deposited for `owner`
+pub fn find_vault(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), VAULT_SEED], &crate::ID)
+}
+
+// also the owner of its own vault, find_vault(multisig)
+pub fn find_multisig(creator: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[creator.as_ref(), nonce.to_le_bytes().as_ref(), MULTISIG_SEED],
+        &crate::ID,
+    )
+}
+
+pub fn find_proposal(multisig: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[multisig.as_ref(), index.to_le_bytes().as_ref(), PROPOSAL_SEED],
+        &crate::ID,
+    )
+}
+
+// listings from before seller + nonce were part of the seeds
+pub fn find_legacy_listing(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), LISTING_SEED], &crate::ID)
+}
+
+// listings from after seller but before nonce was part of the seeds
+pub fn find_legacy_seller_listing(mint: &Pubkey, seller: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), seller.as_ref(), LISTING_SEED], &crate::ID)
+}
+
+pub fn find_program_data() -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[crate::ID.as_ref()],
+        &anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+    )
+}
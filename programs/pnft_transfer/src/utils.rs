@@ -3,7 +3,9 @@ use crate::*;
 #[allow(clippy::too_many_arguments)]
 pub fn send_pnft<'info>(
 
-    authority_and_owner: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    //if passed, transfers as owner's token delegate (e.g. LockedTransfer) instead of as owner
+    delegate: Option<&AccountInfo<'info>>,
     //(!) payer can't carry data, has to be a normal KP:
     // https://github.com/solana-labs/solana/blob/bda0c606a19ce1cc44b5ab638ff0b993f612e76c/runtime/src/system_instruction_processor.rs#L197
     payer: &AccountInfo<'info>,
@@ -13,6 +15,7 @@ pub fn send_pnft<'info>(
     nft_mint: &Account<'info, Mint>,
     nft_metadata: &UncheckedAccount<'info>,
     nft_edition: &UncheckedAccount<'info>,
+    token_metadata_program: &UncheckedAccount<'info>,
     system_program: &Program<'info, System>,
     token_program: &Program<'info, Token>,
     ata_program: &Program<'info, AssociatedToken>,
@@ -22,27 +25,33 @@ pub fn send_pnft<'info>(
     authorization_rules_program: &UncheckedAccount<'info>,
     rules_acc: Option<&AccountInfo<'info>>,
     authorization_data: Option<AuthorizationDataLocal>,
-    //if passed, use signed_invoke() instead of invoke()
-    // program_signer: Option<&Account<'info, TSwap>>,
+    amount: u64,
+    //if passed, use invoke_signed() instead of invoke()
+    signer_seeds: Option<&[&[&[u8]]]>,
 ) -> Result<()> {
+    let authority = delegate.unwrap_or(owner);
     let mut builder = TransferBuilder::new();
 
     builder
-        .authority(*authority_and_owner.key)
-        .token_owner(*authority_and_owner.key)
+        .authority(*authority.key)
+        .token_owner(*owner.key)
         .token(source_ata.key())
         .destination_owner(*dest_owner.key)
         .destination(dest_ata.key())
         .mint(nft_mint.key())
         .metadata(nft_metadata.key())
-        .edition(nft_edition.key())
         .payer(*payer.key);
 
+    //fungible assets don't have an edition, leave it out so TM sees it as missing
+    if !nft_edition.data_is_empty() {
+        builder.edition(nft_edition.key());
+    }
+
     let mut account_infos = vec![
         //   0. `[writable]` Token account
         source_ata.to_account_info(),
         //   1. `[]` Token account owner
-        authority_and_owner.to_account_info(),
+        owner.to_account_info(),
         //   2. `[writable]` Destination token account
         dest_ata.to_account_info(),
         //   3. `[]` Destination token account owner
@@ -54,7 +63,7 @@ pub fn send_pnft<'info>(
         //   6. `[optional]` Edition of token asset
         nft_edition.to_account_info(),
         //   7. `[signer] Transfer authority (token or delegate owner)
-        authority_and_owner.to_account_info(),
+        authority.to_account_info(),
         //   8. `[optional, writable]` Owner record PDA
         //passed in below, if needed
         //   9. `[optional, writable]` Destination record PDA
@@ -73,12 +82,14 @@ pub fn send_pnft<'info>(
         //passed in below, if needed
         //   16. `[optional]` Token Authorization Rules account
         //passed in below, if needed
+        //TM program itself, stands in for whichever optional accounts above are missing
+        token_metadata_program.to_account_info(),
     ];
 
     let metadata = assert_decode_metadata(nft_mint, &nft_metadata.to_account_info())?;
     if let Some(standard) = metadata.token_standard {
         if standard == TokenStandard::ProgrammableNonFungible {
-            msg!("programmable standard triggered");
+            debug_msg!("programmable standard triggered");
             //1. add to builder
             builder
                 .owner_token_record(owner_token_record.key())
@@ -91,47 +102,387 @@ pub fn send_pnft<'info>(
     }
 
     //if auth rules passed in, validate & include it in CPI call
-    if let Some(config) = metadata.programmable_config {
-        match config {
-            V1 { rule_set } => {
-                if let Some(rule_set) = rule_set {
-                    msg!("ruleset triggered");
-                    //safe to unwrap here, it's expected
-                    let rules_acc = rules_acc.unwrap();
-
-                    //1. validate
-                    require!(rule_set == *rules_acc.key, ErrorCode::BadRuleset);
-
-                    //2. add to builder
-                    builder.authorization_rules_program(*authorization_rules_program.key);
-                    builder.authorization_rules(*rules_acc.key);
-
-                    //3. add to accounts
-                    account_infos.push(authorization_rules_program.to_account_info());
-                    account_infos.push(rules_acc.to_account_info());
-                }
-            }
-        }
+    if let Some(rules_acc) = item_rules_acc(&metadata, rules_acc)? {
+        debug_msg!("ruleset triggered");
+        //1. add to builder
+        builder.authorization_rules_program(*authorization_rules_program.key);
+        builder.authorization_rules(*rules_acc.key);
+
+        //2. add to accounts
+        account_infos.push(authorization_rules_program.to_account_info());
+        account_infos.push(rules_acc.to_account_info());
     }
 
-    msg!("authorization data: {:?}", authorization_data);
+    debug_msg!("authorization data: {:?}", authorization_data);
 
     let transfer_ix = builder
         .build(TransferArgs::V1 {
-            amount: 1, //currently 1 only
-            authorization_data: authorization_data
-                .map(|authorization_data| AuthorizationData::try_from(authorization_data).unwrap()),
+            amount,
+            authorization_data: authorization_data.map(AuthorizationData::from),
         })
-        .unwrap()
+        .map_err(|_| ErrorCode::TransferBuilderFailed)?
         .instruction();
 
-    // if let Some(vault) = program_signer {
-    //     invoke_signed(&transfer_ix, &account_infos, &[&program_signer.seeds()])?;
-    // } else {
-    //     invoke(&transfer_ix, &account_infos)?;
-    // }
-    invoke(&transfer_ix, &account_infos)?;
+    if let Some(signer_seeds) = signer_seeds {
+        invoke_signed(&transfer_ix, &account_infos, signer_seeds)?;
+    } else {
+        invoke(&transfer_ix, &account_infos)?;
+    }
+
+    Ok(())
+}
+
+// bundles pass each item's accounts as remaining accounts, BUNDLE_ITEM_ACCOUNTS per item:
+// mint, metadata, edition, source ATA, dest ATA, source token record, dest token record, ruleset
+// (anything, e.g. the auth rules program, when the item doesn't have one)
+pub const BUNDLE_ITEM_ACCOUNTS: usize = 8;
+
+// moves one bundle item, creating the dest ATA if needed. Returns the source ATA so escrows can
+// be closed after
+#[allow(clippy::too_many_arguments)]
+pub fn send_bundle_item<'info>(
+    item_accounts: &[AccountInfo<'info>],
+    owner: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    dest_owner: &AccountInfo<'info>,
+    token_metadata_program: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    token_program: &Program<'info, Token>,
+    ata_program: &Program<'info, AssociatedToken>,
+    instructions: &UncheckedAccount<'info>,
+    authorization_rules_program: &UncheckedAccount<'info>,
+    authorization_data: Option<AuthorizationDataLocal>,
+    signer_seeds: Option<&[&[&[u8]]]>,
+) -> Result<Account<'info, TokenAccount>> {
+    require!(
+        item_accounts.len() == BUNDLE_ITEM_ACCOUNTS,
+        ErrorCode::InvalidBundleSize
+    );
+    let mint = Account::<Mint>::try_from(&item_accounts[0])?;
+    let source_ata = Account::<TokenAccount>::try_from(&item_accounts[3])?;
+
+    // the ATA program checks the address
+    associated_token::create_idempotent(CpiContext::new(
+        ata_program.to_account_info(),
+        associated_token::Create {
+            payer: payer.clone(),
+            associated_token: item_accounts[4].clone(),
+            authority: dest_owner.clone(),
+            mint: mint.to_account_info(),
+            system_program: system_program.to_account_info(),
+            token_program: token_program.to_account_info(),
+        },
+    ))?;
+    let dest_ata = Account::<TokenAccount>::try_from(&item_accounts[4])?;
+
+    send_pnft(
+        owner,
+        None,
+        payer,
+        &source_ata,
+        &dest_ata,
+        dest_owner,
+        &mint,
+        &UncheckedAccount::try_from(item_accounts[1].clone()),
+        &UncheckedAccount::try_from(item_accounts[2].clone()),
+        token_metadata_program,
+        system_program,
+        token_program,
+        ata_program,
+        instructions,
+        &UncheckedAccount::try_from(item_accounts[5].clone()),
+        &UncheckedAccount::try_from(item_accounts[6].clone()),
+        authorization_rules_program,
+        Some(&item_accounts[7]),
+        authorization_data,
+        1,
+        signer_seeds,
+    )?;
+
+    Ok(source_ata)
+}
+
+// the config / collection status PDAs only exist once the admin sets them, missing means not
+// paused / allowed. Items without a verified collection go by the default pubkey's status
+pub fn assert_trading_allowed(
+    config: &AccountInfo,
+    collection_status: &AccountInfo,
+    collection: &Pubkey,
+) -> Result<()> {
+    if !config.data_is_empty() {
+        let config = Account::<MarketConfig>::try_from(config)?;
+        require!(!config.paused, ErrorCode::MarketPaused);
+    }
+
+    require_keys_eq!(
+        *collection_status.key,
+        pda::find_collection_status(collection).0,
+        ErrorCode::BadCollectionStatus
+    );
+    if !collection_status.data_is_empty() {
+        let status = Account::<CollectionStatus>::try_from(collection_status)?;
+        require!(
+            status.status != CollectionState::Frozen,
+            ErrorCode::CollectionFrozen
+        );
+    }
+
+    Ok(())
+}
+
+// list_bundle's per-item checks: the same genuine 1/1 pNFT list_pnft requires, and tradable
+pub fn assert_bundle_item_listable<'info>(
+    item_accounts: &[AccountInfo<'info>],
+    config: &AccountInfo<'info>,
+    collection_status: &AccountInfo<'info>,
+) -> Result<()> {
+    let mint = Account::<Mint>::try_from(&item_accounts[0])?;
+    let metadata = assert_decode_metadata(&mint, &item_accounts[1])?;
+    assert_pnft_master_edition(&mint, &metadata, &item_accounts[2])?;
+    assert_trading_allowed(config, collection_status, &verified_collection(&metadata))
+}
 
+// bundle items can each be from a different collection, checks one item's against the status
+// passed for it
+pub fn assert_bundle_item_trading_allowed<'info>(
+    item_accounts: &[AccountInfo<'info>],
+    config: &AccountInfo<'info>,
+    collection_status: &AccountInfo<'info>,
+) -> Result<()> {
+    let mint = Account::<Mint>::try_from(&item_accounts[0])?;
+    let metadata = assert_decode_metadata(&mint, &item_accounts[1])?;
+    assert_trading_allowed(config, collection_status, &verified_collection(&metadata))
+}
+
+// withdrawals from a vault need `threshold` of its approvers signing too, each counted once
+pub fn assert_vault_approvals(vault: &Vault, signers: &[AccountInfo]) -> Result<()> {
+    let mut approved: Vec<&Pubkey> = vec![];
+    for acc in signers {
+        if acc.is_signer && vault.approvers.contains(acc.key) && !approved.contains(&acc.key) {
+            approved.push(acc.key);
+        }
+    }
+    require!(
+        approved.len() >= vault.threshold as usize,
+        ErrorCode::NotEnoughApprovals
+    );
+    Ok(())
+}
+
+// the ruleset account to hand to TM, if the item has a ruleset. Checked against the metadata
+pub fn item_rules_acc<'a, 'info>(
+    metadata: &Metadata,
+    rules_acc: Option<&'a AccountInfo<'info>>,
+) -> Result<Option<&'a AccountInfo<'info>>> {
+    match metadata.programmable_config {
+        Some(V1 {
+            rule_set: Some(rule_set),
+        }) => {
+            //caller has to pass it when the item has one
+            let rules_acc = rules_acc.ok_or(ErrorCode::BadRuleset)?;
+            require!(rule_set == *rules_acc.key, ErrorCode::BadRuleset);
+            Ok(Some(rules_acc))
+        }
+        _ => Ok(None),
+    }
+}
+
+// everything TM's Delegate / Revoke / Lock / Unlock need for a token delegate (staking etc).
+// The item never leaves owner's token account, the delegate (a PDA of ours) locks it in place
+pub struct TokenDelegateAccounts<'info> {
+    pub owner: AccountInfo<'info>,
+    pub delegate: AccountInfo<'info>,
+    //(!) has to be a normal KP, see send_pnft
+    pub payer: AccountInfo<'info>,
+    pub token: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    pub edition: AccountInfo<'info>,
+    pub token_record: AccountInfo<'info>,
+    pub token_metadata_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub instructions: AccountInfo<'info>,
+    pub authorization_rules_program: AccountInfo<'info>,
+    // already checked with item_rules_acc
+    pub rules_acc: Option<AccountInfo<'info>>,
+}
+
+impl<'info> TokenDelegateAccounts<'info> {
+    fn account_infos(&self) -> Vec<AccountInfo<'info>> {
+        let mut account_infos = vec![
+            self.owner.clone(),
+            self.delegate.clone(),
+            self.payer.clone(),
+            self.token.clone(),
+            self.mint.clone(),
+            self.metadata.clone(),
+            self.edition.clone(),
+            self.token_record.clone(),
+            self.system_program.clone(),
+            self.instructions.clone(),
+            self.token_program.clone(),
+            self.authorization_rules_program.clone(),
+            //TM program itself, stands in for whichever optional accounts are missing
+            self.token_metadata_program.clone(),
+        ];
+        account_infos.extend(self.rules_acc.clone());
+        account_infos
+    }
+}
+
+// owner approves the delegate, owner has to sign the outer txn
+pub fn delegate_token(accounts: &TokenDelegateAccounts, args: DelegateArgs) -> Result<()> {
+    let mut builder = DelegateBuilder::new();
+    builder
+        .delegate(accounts.delegate.key())
+        .metadata(accounts.metadata.key())
+        .master_edition(accounts.edition.key())
+        .token_record(accounts.token_record.key())
+        .mint(accounts.mint.key())
+        .token(accounts.token.key())
+        .authority(accounts.owner.key())
+        .payer(accounts.payer.key())
+        .spl_token_program(accounts.token_program.key());
+    if let Some(rules_acc) = &accounts.rules_acc {
+        builder
+            .authorization_rules_program(accounts.authorization_rules_program.key())
+            .authorization_rules(rules_acc.key());
+    }
+
+    let ix = builder
+        .build(args)
+        .map_err(|_| ErrorCode::InstructionBuilderFailed)?
+        .instruction();
+    invoke(&ix, &accounts.account_infos())?;
+    Ok(())
+}
+
+// owner takes the delegate back off, the item has to be unlocked first
+pub fn revoke_token(accounts: &TokenDelegateAccounts, args: RevokeArgs) -> Result<()> {
+    let mut builder = RevokeBuilder::new();
+    builder
+        .delegate(accounts.delegate.key())
+        .metadata(accounts.metadata.key())
+        .master_edition(accounts.edition.key())
+        .token_record(accounts.token_record.key())
+        .mint(accounts.mint.key())
+        .token(accounts.token.key())
+        .authority(accounts.owner.key())
+        .payer(accounts.payer.key())
+        .spl_token_program(accounts.token_program.key());
+    if let Some(rules_acc) = &accounts.rules_acc {
+        builder
+            .authorization_rules_program(accounts.authorization_rules_program.key())
+            .authorization_rules(rules_acc.key());
+    }
+
+    let ix = builder
+        .build(args)
+        .map_err(|_| ErrorCode::InstructionBuilderFailed)?
+        .instruction();
+    invoke(&ix, &accounts.account_infos())?;
+    Ok(())
+}
+
+// lock / unlock are signed by the delegate, ie with our PDA's seeds
+pub fn lock_token(accounts: &TokenDelegateAccounts, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+    let mut builder = LockBuilder::new();
+    builder
+        .authority(accounts.delegate.key())
+        .token_owner(accounts.owner.key())
+        .token(accounts.token.key())
+        .mint(accounts.mint.key())
+        .metadata(accounts.metadata.key())
+        .edition(accounts.edition.key())
+        .token_record(accounts.token_record.key())
+        .payer(accounts.payer.key())
+        .spl_token_program(accounts.token_program.key());
+    if let Some(rules_acc) = &accounts.rules_acc {
+        builder.authorization_rules(rules_acc.key());
+    }
+
+    let ix = builder
+        .build(LockArgs::V1 {
+            authorization_data: None,
+        })
+        .map_err(|_| ErrorCode::InstructionBuilderFailed)?
+        .instruction();
+    invoke_signed(&ix, &accounts.account_infos(), signer_seeds)?;
+    Ok(())
+}
+
+pub fn unlock_token(accounts: &TokenDelegateAccounts, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+    let mut builder = UnlockBuilder::new();
+    builder
+        .authority(accounts.delegate.key())
+        .token_owner(accounts.owner.key())
+        .token(accounts.token.key())
+        .mint(accounts.mint.key())
+        .metadata(accounts.metadata.key())
+        .edition(accounts.edition.key())
+        .token_record(accounts.token_record.key())
+        .payer(accounts.payer.key())
+        .spl_token_program(accounts.token_program.key());
+    if let Some(rules_acc) = &accounts.rules_acc {
+        builder.authorization_rules(rules_acc.key());
+    }
+
+    let ix = builder
+        .build(UnlockArgs::V1 {
+            authorization_data: None,
+        })
+        .map_err(|_| ErrorCode::InstructionBuilderFailed)?
+        .instruction();
+    invoke_signed(&ix, &accounts.account_infos(), signer_seeds)?;
+    Ok(())
+}
+
+// for emptied escrows owned by a program PDA, rent goes to destination
+pub fn close_token_account<'info>(
+    token_account: &Account<'info, TokenAccount>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: token_account.to_account_info(),
+            destination: destination.clone(),
+            authority: authority.clone(),
+        },
+        signer_seeds,
+    ))
+}
+
+// the memo program checks `signer` actually signed, it has to be a signer of this ix already
+pub fn write_memo<'info>(
+    memo: &str,
+    signer: &AccountInfo<'info>,
+    memo_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let memo_ix = spl_memo::build_memo(memo.as_bytes(), &[signer.key]);
+    invoke(&memo_ix, &[signer.clone(), memo_program.clone()])?;
+    Ok(())
+}
+
+// lamports held by one of our PDAs (not rent), can't go through the system program since it has data
+pub fn transfer_lamports_from_pda(
+    pda: &AccountInfo,
+    destination: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(pda.data_len());
+    let available = pda.lamports().saturating_sub(rent);
+    require!(amount <= available, ErrorCode::InsufficientFunds);
+
+    **pda.try_borrow_mut_lamports()? -= amount;
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
     Ok(())
 }
 
@@ -140,21 +491,60 @@ pub fn assert_decode_metadata<'info>(
     nft_mint: &Account<'info, Mint>,
     metadata_account: &AccountInfo<'info>,
 ) -> Result<Metadata> {
-    let (key, _) = Pubkey::find_program_address(
-        &[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            nft_mint.key().as_ref(),
-        ],
-        &mpl_token_metadata::id(),
-    );
-    if key != *metadata_account.key {
-        require!(true, ErrorCode::BadMetadata);
-    }
-    // Check account owner (redundant because of find_program_address above, but why not).
+    let (key, _) = pda::find_metadata(&nft_mint.key());
+    require_keys_eq!(key, *metadata_account.key, ErrorCode::BadMetadata);
+    // Check account owner (redundant because of the address check above, but why not).
     if *metadata_account.owner != mpl_token_metadata::id() {
         return Err(error!(ErrorCode::BadMetadata));
     }
 
     Ok(Metadata::from_account_info(metadata_account)?)
 }
+
+// listings (and bundles, rentals, loan collateral) only take genuine 1/1 pNFTs: the programmable
+// standard, a master edition that can't print, and a mint whose authorities were handed to that
+// edition. Fungibles and prints of somebody else's master edition (or a made-up "edition") fail
+// here.
+// primary_sale_happened isn't checked: Token Metadata lets any holder of the token set it
+// (UpdatePrimarySaleHappenedViaToken), so it says nothing about the lister, and requiring it
+// would lock out holders of items whose creator never flipped it
+pub fn assert_pnft_master_edition<'info>(
+    nft_mint: &Account<'info, Mint>,
+    metadata: &Metadata,
+    edition: &AccountInfo<'info>,
+) -> Result<()> {
+    require_keys_eq!(metadata.mint, nft_mint.key(), ErrorCode::BadMetadata);
+    require!(
+        metadata.token_standard == Some(TokenStandard::ProgrammableNonFungible),
+        ErrorCode::NotProgrammable
+    );
+    require!(
+        nft_mint.supply == 1 && nft_mint.decimals == 0,
+        ErrorCode::InvalidItem
+    );
+
+    // from_account_info checks the owner and the account key (MasterEditionV2)
+    let master_edition =
+        MasterEditionV2::from_account_info(edition).map_err(|_| error!(ErrorCode::BadEdition))?;
+    require!(
+        master_edition.max_supply == Some(0),
+        ErrorCode::BadEdition
+    );
+    require!(
+        nft_mint.mint_authority == COption::Some(edition.key())
+            && nft_mint.freeze_authority == COption::Some(edition.key()),
+        ErrorCode::BadEdition
+    );
+
+    Ok(())
+}
+
+// default pubkey when there's no collection or it isn't verified
+pub fn verified_collection(metadata: &Metadata) -> Pubkey {
+    metadata
+        .collection
+        .as_ref()
+        .filter(|collection| collection.verified)
+        .map(|collection| collection.key)
+        .unwrap_or_default()
+}
@@ -0,0 +1,1686 @@
+// Shared setup for the program-test suites: loads the Token Metadata / Token Auth Rules builds
+// bundled in tests/programs, mints pNFTs and builds pnft_transfer ixs.
+//
+// Every test binary only uses part of this.
+#![allow(dead_code)]
+
+pub use anchor_lang::{
+    error::ERROR_CODE_OFFSET, AccountDeserialize, AccountSerialize, InstructionData,
+    ToAccountMetas,
+};
+pub use anchor_spl::associated_token::{self, get_associated_token_address};
+pub use anchor_spl::token::{self, spl_token};
+pub use mpl_token_auth_rules::{
+    instruction::{
+        builders::CreateOrUpdateBuilder, CreateOrUpdateArgs,
+        InstructionBuilder as AuthRulesInstructionBuilder,
+    },
+    state::{Rule, RuleSetV1},
+};
+pub use mpl_token_metadata::{
+    instruction::{
+        builders::{CreateBuilder, MintBuilder},
+        CreateArgs, InstructionBuilder, MintArgs,
+    },
+    state::{AssetData, Collection, PrintSupply, TokenStandard},
+};
+pub use pnft_transfer::{
+    errors::ErrorCode, pda, Bundle, LEGACY_LISTING_SIZE, PRE_VERSION_LISTING_SIZE, CollectionMultiplier, CollectionState, Listing, Loan,
+    MarketConfig, Multisig, Proposal, Rental, StakeRecord, Vault,
+};
+pub use solana_program_test::{
+    find_file, processor, tokio, BanksClientError, ProgramTest, ProgramTestContext,
+};
+pub use solana_sdk::{
+    account::{Account, AccountSharedData, WritableAccount},
+    account_utils::StateMut,
+    bpf_loader,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    program_pack::Pack,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+const RULE_SET_NAME: &str = "pnft_transfer_tests";
+
+// ----------------------------------------------------------------------------- setup
+
+pub fn add_bundled_program(program_test: &mut ProgramTest, name: &str, program_id: Pubkey) {
+    let path = format!(
+        "{}/../../tests/programs/{}.so",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    let data = std::fs::read(&path).unwrap_or_else(|e| panic!("couldn't read {}: {}", path, e));
+    program_test.add_account(
+        program_id,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()).max(1),
+            data,
+            owner: bpf_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+}
+
+fn add_metaplex_programs(program_test: &mut ProgramTest) {
+    add_bundled_program(program_test, "mpl_token_metadata", mpl_token_metadata::ID);
+    add_bundled_program(program_test, "mpl_token_auth_rules", mpl_token_auth_rules::ID);
+}
+
+pub async fn setup() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new(
+        "pnft_transfer",
+        pnft_transfer::ID,
+        processor!(pnft_transfer::entry),
+    );
+    add_metaplex_programs(&mut program_test);
+    program_test.start_with_context().await
+}
+
+// pnft_transfer deployed through the upgradeable loader with `upgrade_authority`, for the ixs
+// gated on it. Under test-sbf the ProgramData holds the built .so. Natively there's no ELF to run,
+// so our processor stands in for the upgradeable loader, which then dispatches straight to it
+pub async fn setup_upgradeable(upgrade_authority: &Pubkey) -> ProgramTestContext {
+    let mut program_test = ProgramTest::default();
+    add_metaplex_programs(&mut program_test);
+
+    let elf = if std::env::var("BPF_OUT_DIR").is_ok() || std::env::var("SBF_OUT_DIR").is_ok() {
+        let path = find_file("pnft_transfer.so").expect("pnft_transfer.so not built");
+        std::fs::read(path).unwrap()
+    } else {
+        program_test.add_builtin_program(
+            "pnft_transfer",
+            bpf_loader_upgradeable::id(),
+            processor!(pnft_transfer::entry).unwrap(),
+        );
+        vec![]
+    };
+
+    let program_data = pda::find_program_data().0;
+    let mut program = AccountSharedData::new(
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_program()),
+        UpgradeableLoaderState::size_of_program(),
+        &bpf_loader_upgradeable::id(),
+    );
+    program
+        .set_state(&UpgradeableLoaderState::Program {
+            programdata_address: program_data,
+        })
+        .unwrap();
+    program.set_executable(true);
+    program_test.add_account(pnft_transfer::ID, program.into());
+
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let mut data = AccountSharedData::new(
+        Rent::default().minimum_balance(metadata_len + elf.len()),
+        metadata_len + elf.len(),
+        &bpf_loader_upgradeable::id(),
+    );
+    data.set_state(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(*upgrade_authority),
+    })
+    .unwrap();
+    data.data_as_mut_slice()[metadata_len..].copy_from_slice(&elf);
+    program_test.add_account(program_data, data.into());
+
+    program_test.start_with_context().await
+}
+
+async fn transaction(
+    ctx: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Transaction {
+    // pnft transfers go way past the default 200k
+    let mut all_ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_000_000)];
+    all_ixs.extend_from_slice(ixs);
+
+    let mut all_signers = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+
+    let blockhash = ctx.banks_client.get_latest_blockhash().await.unwrap();
+    Transaction::new_signed_with_payer(&all_ixs, Some(&ctx.payer.pubkey()), &all_signers, blockhash)
+}
+
+pub async fn process(
+    ctx: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let tx = transaction(ctx, ixs, signers).await;
+    ctx.banks_client.process_transaction(tx).await
+}
+
+pub struct Simulation {
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    // whole txn, the compute budget ix included
+    pub units_consumed: u64,
+}
+
+// runs the txn without committing it, for the logs and CUs
+pub async fn simulate(
+    ctx: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Simulation {
+    let tx = transaction(ctx, ixs, signers).await;
+    let simulation = ctx.banks_client.simulate_transaction(tx).await.unwrap();
+    let details = simulation.simulation_details.unwrap();
+    Simulation {
+        result: simulation.result.unwrap(),
+        logs: details.logs,
+        units_consumed: details.units_consumed,
+    }
+}
+
+pub fn assert_custom_err(result: Result<(), BanksClientError>, code: u32) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(c)) => assert_eq!(c, code),
+        e => panic!("expected custom error {}, got {:?}", code, e),
+    }
+}
+
+pub fn program_err(code: ErrorCode) -> u32 {
+    ERROR_CODE_OFFSET + code as u32
+}
+
+pub fn anchor_err(code: anchor_lang::error::ErrorCode) -> u32 {
+    code as u32
+}
+
+pub async fn funded_wallet(ctx: &mut ProgramTestContext, lamports: u64) -> Keypair {
+    let wallet = Keypair::new();
+    let ix = system_instruction::transfer(&ctx.payer.pubkey(), &wallet.pubkey(), lamports);
+    process(ctx, &[ix], &[]).await.unwrap();
+    wallet
+}
+
+pub async fn lamports(ctx: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    ctx.banks_client.get_balance(*address).await.unwrap()
+}
+
+pub async fn token_balance(ctx: &mut ProgramTestContext, token_account: &Pubkey) -> Option<u64> {
+    let account = ctx
+        .banks_client
+        .get_account(*token_account)
+        .await
+        .unwrap()?;
+    let token_account = token::TokenAccount::try_deserialize(&mut account.data.as_slice()).unwrap();
+    Some(token_account.amount)
+}
+
+pub async fn create_ata(ctx: &mut ProgramTestContext, wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let ix = spl_associated_token_account::instruction::create_associated_token_account(
+        &ctx.payer.pubkey(),
+        wallet,
+        mint,
+        &spl_token::ID,
+    );
+    process(ctx, &[ix], &[]).await.unwrap();
+    get_associated_token_address(wallet, mint)
+}
+
+// ----------------------------------------------------------------------------- metaplex
+
+// plain SPL mint, e.g. for rewards
+pub async fn create_mint(ctx: &mut ProgramTestContext, authority: &Pubkey) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &ctx.payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), authority, None, 0)
+            .unwrap(),
+    ];
+    process(ctx, &ixs, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+pub async fn warp_seconds(ctx: &mut ProgramTestContext, seconds: i64) -> i64 {
+    // a new slot for a new blockhash, otherwise a txn retried after the warp has the same
+    // signature and gets the earlier result back
+    let slot = ctx.banks_client.get_root_slot().await.unwrap();
+    ctx.warp_to_slot(slot + 1).unwrap();
+    let mut clock: Clock = ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    ctx.set_sysvar(&clock);
+    clock.unix_timestamp
+}
+
+pub async fn create_rule_set(ctx: &mut ProgramTestContext, transfer_rule: Rule) -> Pubkey {
+    let owner = ctx.payer.pubkey();
+    let (rule_set, _) =
+        mpl_token_auth_rules::pda::find_rule_set_address(owner, RULE_SET_NAME.to_string());
+
+    let mut rules = RuleSetV1::new(RULE_SET_NAME.to_string(), owner);
+    rules
+        .add("Transfer:Holder".to_string(), transfer_rule)
+        .unwrap();
+    let mut serialized_rule_set = vec![];
+    rmp_serde::encode::write(&mut serialized_rule_set, &rules).unwrap();
+
+    let ix = CreateOrUpdateBuilder::new()
+        .payer(owner)
+        .rule_set_pda(rule_set)
+        .build(CreateOrUpdateArgs::V1 {
+            serialized_rule_set,
+        })
+        .unwrap()
+        .instruction();
+    process(ctx, &[ix], &[]).await.unwrap();
+    rule_set
+}
+
+// mints a fresh pNFT into owner's ATA, the test payer is the update authority
+pub async fn mint_pnft(
+    ctx: &mut ProgramTestContext,
+    owner: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Pubkey {
+    mint_asset(ctx, owner, TokenStandard::ProgrammableNonFungible, rule_set, None).await
+}
+
+// a pNFT with `collection` set and verified by the payer (the collection's update authority)
+pub async fn mint_pnft_in_collection(
+    ctx: &mut ProgramTestContext,
+    owner: &Pubkey,
+    rule_set: Option<Pubkey>,
+    collection: &Pubkey,
+) -> Pubkey {
+    let mint = mint_asset(
+        ctx,
+        owner,
+        TokenStandard::ProgrammableNonFungible,
+        rule_set,
+        Some(*collection),
+    )
+    .await;
+    let payer = ctx.payer.pubkey();
+    let ix = mpl_token_metadata::instruction::verify_collection(
+        mpl_token_metadata::ID,
+        pda::find_metadata(&mint).0,
+        payer,
+        payer,
+        *collection,
+        pda::find_metadata(collection).0,
+        pda::find_edition(collection).0,
+        None,
+    );
+    process(ctx, &[ix], &[]).await.unwrap();
+    mint
+}
+
+// plain NFT held by the payer, to verify items against
+pub async fn create_collection(ctx: &mut ProgramTestContext) -> Pubkey {
+    let payer = ctx.payer.pubkey();
+    mint_asset(ctx, &payer, TokenStandard::NonFungible, None, None).await
+}
+
+pub async fn mint_asset(
+    ctx: &mut ProgramTestContext,
+    owner: &Pubkey,
+    token_standard: TokenStandard,
+    rule_set: Option<Pubkey>,
+    collection: Option<Pubkey>,
+) -> Pubkey {
+    mint_asset_with_print_supply(
+        ctx,
+        owner,
+        token_standard,
+        rule_set,
+        collection,
+        PrintSupply::Zero,
+    )
+    .await
+}
+
+// same, but the master edition can print `print_supply` editions
+pub async fn mint_asset_with_print_supply(
+    ctx: &mut ProgramTestContext,
+    owner: &Pubkey,
+    token_standard: TokenStandard,
+    rule_set: Option<Pubkey>,
+    collection: Option<Pubkey>,
+    print_supply: PrintSupply,
+) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = ctx.payer.pubkey();
+    let metadata = pda::find_metadata(&mint.pubkey()).0;
+    let edition = pda::find_edition(&mint.pubkey()).0;
+    let token = get_associated_token_address(owner, &mint.pubkey());
+
+    let mut asset_data = AssetData::new(
+        token_standard,
+        "pnft".to_string(),
+        "PNFT".to_string(),
+        "https://example.com/pnft.json".to_string(),
+    );
+    asset_data.rule_set = rule_set;
+    asset_data.collection = collection.map(|key| Collection {
+        verified: false,
+        key,
+    });
+
+    let create_ix = CreateBuilder::new()
+        .metadata(metadata)
+        .master_edition(edition)
+        .mint(mint.pubkey())
+        .authority(payer)
+        .payer(payer)
+        .update_authority(payer)
+        .initialize_mint(true)
+        .update_authority_as_signer(true)
+        .build(CreateArgs::V1 {
+            asset_data,
+            decimals: Some(0),
+            print_supply: Some(print_supply),
+        })
+        .unwrap()
+        .instruction();
+
+    let mut mint_builder = MintBuilder::new();
+    mint_builder
+        .token(token)
+        .token_owner(*owner)
+        .metadata(metadata)
+        .master_edition(edition)
+        .mint(mint.pubkey())
+        .authority(payer)
+        .payer(payer);
+    if token_standard == TokenStandard::ProgrammableNonFungible {
+        mint_builder.token_record(pda::find_token_record(&mint.pubkey(), &token).0);
+    }
+    if let Some(rule_set) = rule_set {
+        mint_builder.authorization_rules(rule_set);
+    }
+    let mint_ix = mint_builder
+        .build(MintArgs::V1 {
+            amount: 1,
+            authorization_data: None,
+        })
+        .unwrap()
+        .instruction();
+
+    process(ctx, &[create_ix, mint_ix], &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+// ----------------------------------------------------------------------------- pnft_transfer ixs
+
+pub fn rule_set_metas(rule_set: Option<Pubkey>) -> Vec<anchor_lang::prelude::AccountMeta> {
+    rule_set
+        .map(|rule_set| {
+            vec![anchor_lang::prelude::AccountMeta::new_readonly(
+                rule_set, false,
+            )]
+        })
+        .unwrap_or_default()
+}
+
+// one item's slice of a bundle ix's remaining accounts, moving it from `from`'s ATA to `to`'s.
+// Items without a ruleset get the auth rules program in the ruleset slot
+pub fn bundle_item_metas(
+    mint: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Vec<anchor_lang::prelude::AccountMeta> {
+    use anchor_lang::prelude::AccountMeta;
+    let from_ata = get_associated_token_address(from, mint);
+    let to_ata = get_associated_token_address(to, mint);
+    vec![
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(pda::find_metadata(mint).0, false),
+        AccountMeta::new_readonly(pda::find_edition(mint).0, false),
+        AccountMeta::new(from_ata, false),
+        AccountMeta::new(to_ata, false),
+        AccountMeta::new(pda::find_token_record(mint, &from_ata).0, false),
+        AccountMeta::new(pda::find_token_record(mint, &to_ata).0, false),
+        AccountMeta::new_readonly(rule_set.unwrap_or(mpl_token_auth_rules::ID), false),
+    ]
+}
+
+pub fn ix(
+    mut accounts: Vec<anchor_lang::prelude::AccountMeta>,
+    remaining: Vec<anchor_lang::prelude::AccountMeta>,
+    data: impl InstructionData,
+) -> Instruction {
+    accounts.extend(remaining);
+    Instruction {
+        program_id: pnft_transfer::ID,
+        accounts,
+        data: data.data(),
+    }
+}
+
+pub fn transfer_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    receiver: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let src = get_associated_token_address(owner, mint);
+    let dest = get_associated_token_address(receiver, mint);
+    let accounts = pnft_transfer::accounts::TransferPNFT {
+        owner: *owner,
+        receiver: *receiver,
+        src,
+        dest,
+        nft_mint: *mint,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        nft_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        owner_token_record: pda::find_token_record(mint, &src).0,
+        dest_token_record: pda::find_token_record(mint, &dest).0,
+        pnft_shared: pnft_transfer::accounts::ProgNftShared {
+            token_metadata_program: mpl_token_metadata::ID,
+            instructions: sysvar::instructions::ID,
+            authorization_rules_program: mpl_token_auth_rules::ID,
+        },
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::TransferPnft {
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+            memo: None,
+        },
+    )
+}
+
+// payer covers the dest token record instead of the owner, it signs in the last remaining account
+pub fn sponsored_transfer_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    receiver: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let mut ix = transfer_ix(mint, owner, receiver, rule_set);
+    ix.accounts.push(anchor_lang::prelude::AccountMeta::new(*payer, true));
+    ix
+}
+
+// also passes the SPL Memo program, so the memo goes through it too
+pub fn transfer_with_memo_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    receiver: &Pubkey,
+    memo: &str,
+) -> Instruction {
+    let mut ix = transfer_ix(mint, owner, receiver, None);
+    ix.data = pnft_transfer::instruction::TransferPnft {
+        authorization_data: None,
+        rules_acc_present: false,
+        memo: Some(memo.to_string()),
+    }
+    .data();
+    ix.accounts
+        .push(anchor_lang::prelude::AccountMeta::new_readonly(spl_memo::id(), false));
+    ix
+}
+
+pub fn list_ix(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    price: u64,
+    nonce: u64,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let listing = pda::find_listing(mint, seller, nonce).0;
+    let authority_item_token = get_associated_token_address(seller, mint);
+    let listing_item_token = get_associated_token_address(&listing, mint);
+    let accounts = pnft_transfer::accounts::ListPNFT {
+        item: *mint,
+        authority_item_token,
+        listing,
+        listing_item_token,
+        authority: *seller,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(&Pubkey::default()).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        authority_token_record: pda::find_token_record(mint, &authority_item_token).0,
+        listing_token_record: pda::find_token_record(mint, &listing_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ListPnft {
+            price,
+            nonce,
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn buy_ix(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    buyer: &Pubkey,
+    nonce: u64,
+    expected_price: u64,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let listing = pda::find_listing(mint, seller, nonce).0;
+    let listing_item_token = get_associated_token_address(&listing, mint);
+    let buyer_item_token = get_associated_token_address(buyer, mint);
+    let accounts = pnft_transfer::accounts::BuyPNFT {
+        item: *mint,
+        buyer_item_token,
+        listing,
+        seller: *seller,
+        listing_item_token,
+        buyer: *buyer,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(&Pubkey::default()).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        buyer_token_record: pda::find_token_record(mint, &buyer_item_token).0,
+        listing_token_record: pda::find_token_record(mint, &listing_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+        ruleset: rule_set.unwrap_or(mpl_token_metadata::ID),
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::BuyPnft {
+            expected_price,
+            expected_seller: *seller,
+            expected_nonce: Some(nonce),
+        },
+    )
+}
+
+pub fn cancel_listing_ix(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let listing = pda::find_listing(mint, seller, nonce).0;
+    let listing_item_token = get_associated_token_address(&listing, mint);
+    let seller_item_token = get_associated_token_address(seller, mint);
+    let accounts = pnft_transfer::accounts::CancelListing {
+        item: *mint,
+        listing,
+        listing_item_token,
+        seller: *seller,
+        seller_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        listing_token_record: pda::find_token_record(mint, &listing_item_token).0,
+        seller_token_record: pda::find_token_record(mint, &seller_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::CancelListing {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn update_price_ix(mint: &Pubkey, seller: &Pubkey, nonce: u64, new_price: u64) -> Instruction {
+    let accounts = pnft_transfer::accounts::UpdatePrice {
+        listing: pda::find_listing(mint, seller, nonce).0,
+        seller: *seller,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::UpdatePrice { new_price },
+    )
+}
+
+pub fn stake_ix(mint: &Pubkey, owner: &Pubkey, rule_set: Option<Pubkey>) -> Instruction {
+    let item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::StakePNFT {
+        item: *mint,
+        item_token,
+        stake_record: pda::find_stake_record(mint, owner).0,
+        owner: *owner,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        item_token_record: pda::find_token_record(mint, &item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::StakePnft {
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn unstake_ix(mint: &Pubkey, owner: &Pubkey, rule_set: Option<Pubkey>) -> Instruction {
+    let item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::UnstakePNFT {
+        item: *mint,
+        item_token,
+        stake_record: pda::find_stake_record(mint, owner).0,
+        owner: *owner,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        item_token_record: pda::find_token_record(mint, &item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::UnstakePnft {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn init_reward_pool_ix(
+    authority: &Pubkey,
+    reward_mint: &Pubkey,
+    rate_per_second: u64,
+    multipliers: Vec<CollectionMultiplier>,
+) -> Instruction {
+    let reward_pool = pda::find_reward_pool(authority, reward_mint).0;
+    let accounts = pnft_transfer::accounts::InitRewardPool {
+        reward_pool,
+        reward_mint: *reward_mint,
+        vault: get_associated_token_address(&reward_pool, reward_mint),
+        authority: *authority,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::InitRewardPool {
+            rate_per_second,
+            multipliers,
+        },
+    )
+}
+
+pub fn claim_rewards_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    pool_authority: &Pubkey,
+    reward_mint: &Pubkey,
+) -> Instruction {
+    let reward_pool = pda::find_reward_pool(pool_authority, reward_mint).0;
+    let accounts = pnft_transfer::accounts::ClaimRewards {
+        item: *mint,
+        stake_record: pda::find_stake_record(mint, owner).0,
+        reward_pool,
+        reward_mint: *reward_mint,
+        vault: get_associated_token_address(&reward_pool, reward_mint),
+        owner_reward_token: get_associated_token_address(owner, reward_mint),
+        owner: *owner,
+        item_metadata: pda::find_metadata(mint).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::ClaimRewards {},
+    )
+}
+
+pub fn offer_loan_ix(
+    lender: &Pubkey,
+    nonce: u64,
+    collection: &Pubkey,
+    principal: u64,
+    apr_bps: u32,
+    duration: i64,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::OfferLoan {
+        loan: pda::find_loan(lender, nonce).0,
+        lender: *lender,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(collection).0,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::OfferLoan {
+            nonce,
+            collection: *collection,
+            principal,
+            apr_bps,
+            duration,
+        },
+    )
+}
+
+pub fn take_loan_ix(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    collection: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = pda::find_loan(lender, nonce).0;
+    let borrower_item_token = get_associated_token_address(borrower, mint);
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let accounts = pnft_transfer::accounts::TakeLoan {
+        item: *mint,
+        borrower_item_token,
+        loan,
+        loan_item_token,
+        borrower: *borrower,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(collection).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        borrower_token_record: pda::find_token_record(mint, &borrower_item_token).0,
+        loan_token_record: pda::find_token_record(mint, &loan_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::TakeLoan {
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn repay_loan_ix(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = pda::find_loan(lender, nonce).0;
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let borrower_item_token = get_associated_token_address(borrower, mint);
+    let accounts = pnft_transfer::accounts::RepayLoan {
+        item: *mint,
+        loan,
+        loan_item_token,
+        lender: *lender,
+        borrower: *borrower,
+        borrower_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        loan_token_record: pda::find_token_record(mint, &loan_item_token).0,
+        borrower_token_record: pda::find_token_record(mint, &borrower_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::RepayLoan {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn foreclose_loan_ix(
+    mint: &Pubkey,
+    lender: &Pubkey,
+    nonce: u64,
+    borrower: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let loan = pda::find_loan(lender, nonce).0;
+    let loan_item_token = get_associated_token_address(&loan, mint);
+    let lender_item_token = get_associated_token_address(lender, mint);
+    let accounts = pnft_transfer::accounts::ForecloseLoan {
+        item: *mint,
+        loan,
+        loan_item_token,
+        lender: *lender,
+        borrower: *borrower,
+        lender_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        loan_token_record: pda::find_token_record(mint, &loan_item_token).0,
+        lender_token_record: pda::find_token_record(mint, &lender_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ForecloseLoan {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn list_for_rent_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    daily_price: u64,
+    max_days: u16,
+    collection: Option<Pubkey>,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let rental = pda::find_rental(mint, owner).0;
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let accounts = pnft_transfer::accounts::ListForRent {
+        item: *mint,
+        owner_item_token,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(&collection.unwrap_or_default()).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        owner_token_record: pda::find_token_record(mint, &owner_item_token).0,
+        rental_token_record: pda::find_token_record(mint, &rental_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::ListForRent {
+            daily_price,
+            max_days,
+            authorization_data: None,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn cancel_rental_ix(mint: &Pubkey, owner: &Pubkey, rule_set: Option<Pubkey>) -> Instruction {
+    let rental = pda::find_rental(mint, owner).0;
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::CancelRental {
+        item: *mint,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        owner_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        rental_token_record: pda::find_token_record(mint, &rental_item_token).0,
+        owner_token_record: pda::find_token_record(mint, &owner_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::CancelRental {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn rent_pnft_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    renter: &Pubkey,
+    days: u16,
+    collection: Option<Pubkey>,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let rental = pda::find_rental(mint, owner).0;
+    let rental_item_token = get_associated_token_address(&rental, mint);
+    let renter_item_token = get_associated_token_address(renter, mint);
+    let accounts = pnft_transfer::accounts::RentPNFT {
+        item: *mint,
+        rental,
+        rental_item_token,
+        owner: *owner,
+        renter: *renter,
+        renter_item_token,
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(&collection.unwrap_or_default()).0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        rental_token_record: pda::find_token_record(mint, &rental_item_token).0,
+        renter_token_record: pda::find_token_record(mint, &renter_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::RentPnft {
+            days,
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+pub fn end_rental_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    renter: &Pubkey,
+    payer: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let renter_item_token = get_associated_token_address(renter, mint);
+    let owner_item_token = get_associated_token_address(owner, mint);
+    let accounts = pnft_transfer::accounts::EndRental {
+        item: *mint,
+        rental: pda::find_rental(mint, owner).0,
+        owner: *owner,
+        renter: *renter,
+        renter_item_token,
+        owner_item_token,
+        payer: *payer,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        renter_token_record: pda::find_token_record(mint, &renter_item_token).0,
+        owner_token_record: pda::find_token_record(mint, &owner_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::EndRental {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+// each item's slice, then their collection statuses, all items in `collection` (None if unverified)
+fn bundle_metas(
+    items: &[Pubkey],
+    collection: Option<Pubkey>,
+    from: &Pubkey,
+    to: &Pubkey,
+) -> Vec<anchor_lang::prelude::AccountMeta> {
+    let collection_status = pda::find_collection_status(&collection.unwrap_or_default()).0;
+    let mut metas: Vec<_> = items
+        .iter()
+        .flat_map(|mint| bundle_item_metas(mint, from, to, None))
+        .collect();
+    metas.extend(
+        items
+            .iter()
+            .map(|_| anchor_lang::prelude::AccountMeta::new_readonly(collection_status, false)),
+    );
+    metas
+}
+
+pub fn list_bundle_ix(
+    seller: &Pubkey,
+    nonce: u64,
+    price: u64,
+    items: &[Pubkey],
+    collection: Option<Pubkey>,
+) -> Instruction {
+    let bundle = pda::find_bundle(seller, nonce).0;
+    let accounts = pnft_transfer::accounts::ListBundle {
+        bundle,
+        seller: *seller,
+        config: pda::find_config().0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        bundle_metas(items, collection, seller, &bundle),
+        pnft_transfer::instruction::ListBundle {
+            nonce,
+            price,
+            authorization_data: None,
+        },
+    )
+}
+
+pub fn buy_bundle_ix(
+    seller: &Pubkey,
+    nonce: u64,
+    buyer: &Pubkey,
+    items: &[Pubkey],
+    collection: Option<Pubkey>,
+) -> Instruction {
+    let bundle = pda::find_bundle(seller, nonce).0;
+    let accounts = pnft_transfer::accounts::BuyBundle {
+        bundle,
+        seller: *seller,
+        buyer: *buyer,
+        config: pda::find_config().0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        bundle_metas(items, collection, &bundle, buyer),
+        pnft_transfer::instruction::BuyBundle {},
+    )
+}
+
+pub fn cancel_bundle_ix(seller: &Pubkey, nonce: u64, items: &[Pubkey]) -> Instruction {
+    let bundle = pda::find_bundle(seller, nonce).0;
+    let accounts = pnft_transfer::accounts::CancelBundle {
+        bundle,
+        seller: *seller,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        items
+            .iter()
+            .flat_map(|mint| bundle_item_metas(mint, &bundle, seller, None))
+            .collect(),
+        pnft_transfer::instruction::CancelBundle {},
+    )
+}
+
+pub fn set_paused_ix(authority: &Pubkey, paused: bool) -> Instruction {
+    let accounts = pnft_transfer::accounts::UpdateConfig {
+        config: pda::find_config().0,
+        authority: *authority,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::SetPaused { paused },
+    )
+}
+
+pub fn set_collection_status_ix(
+    authority: &Pubkey,
+    collection: &Pubkey,
+    status: CollectionState,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::SetCollectionStatus {
+        config: pda::find_config().0,
+        collection_status: pda::find_collection_status(collection).0,
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::SetCollectionStatus {
+            collection: *collection,
+            status,
+        },
+    )
+}
+
+// init_config needs an upgrade authority, which the natively loaded program doesn't have, so the
+// config is written in directly
+pub async fn set_config(ctx: &mut ProgramTestContext, authority: &Pubkey) {
+    let (config, bump) = pda::find_config();
+    let mut data = vec![];
+    MarketConfig {
+        bump,
+        authority: *authority,
+        paused: false,
+        reserved: [0; pnft_transfer::CONFIG_RESERVED_SIZE],
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    data.resize(MarketConfig::SIZE, 0);
+    let account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: pnft_transfer::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    ctx.set_account(&config, &account.into());
+}
+
+pub fn init_vault_ix(owner: &Pubkey, approvers: Vec<Pubkey>, threshold: u8) -> Instruction {
+    let accounts = pnft_transfer::accounts::InitVault {
+        vault: pda::find_vault(owner).0,
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::InitVault {
+            approvers,
+            threshold,
+        },
+    )
+}
+
+pub fn deposit_ix(
+    mint: &Pubkey,
+    depositor: &Pubkey,
+    owner: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let vault = pda::find_vault(owner).0;
+    let depositor_item_token = get_associated_token_address(depositor, mint);
+    let vault_item_token = get_associated_token_address(&vault, mint);
+    let accounts = pnft_transfer::accounts::Deposit {
+        item: *mint,
+        depositor_item_token,
+        vault,
+        vault_item_token,
+        depositor: *depositor,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        depositor_token_record: pda::find_token_record(mint, &depositor_item_token).0,
+        vault_token_record: pda::find_token_record(mint, &vault_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        rule_set_metas(rule_set),
+        pnft_transfer::instruction::Deposit {
+            rules_acc_present: rule_set.is_some(),
+        },
+    )
+}
+
+// withdraw_to `receiver`, or withdraw when it's the owner. Approvers go after the ruleset as signers
+pub fn withdraw_ix(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    receiver: &Pubkey,
+    approvers: &[Pubkey],
+    rule_set: Option<Pubkey>,
+) -> Instruction {
+    let vault = pda::find_vault(owner).0;
+    let vault_item_token = get_associated_token_address(&vault, mint);
+    let receiver_item_token = get_associated_token_address(receiver, mint);
+    let accounts = pnft_transfer::accounts::Withdraw {
+        item: *mint,
+        vault,
+        vault_item_token,
+        owner: *owner,
+        receiver: *receiver,
+        receiver_item_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        vault_token_record: pda::find_token_record(mint, &vault_item_token).0,
+        receiver_token_record: pda::find_token_record(mint, &receiver_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    let mut remaining = rule_set_metas(rule_set);
+    remaining.extend(
+        approvers
+            .iter()
+            .map(|approver| anchor_lang::prelude::AccountMeta::new_readonly(*approver, true)),
+    );
+    let rules_acc_present = rule_set.is_some();
+    if receiver == owner {
+        ix(
+            accounts.to_account_metas(None),
+            remaining,
+            pnft_transfer::instruction::Withdraw { rules_acc_present },
+        )
+    } else {
+        ix(
+            accounts.to_account_metas(None),
+            remaining,
+            pnft_transfer::instruction::WithdrawTo { rules_acc_present },
+        )
+    }
+}
+
+pub fn create_multisig_ix(
+    creator: &Pubkey,
+    nonce: u64,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+    timelock: i64,
+) -> Instruction {
+    let multisig = pda::find_multisig(creator, nonce).0;
+    let accounts = pnft_transfer::accounts::CreateMultisig {
+        multisig,
+        vault: pda::find_vault(&multisig).0,
+        creator: *creator,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::CreateMultisig {
+            nonce,
+            signers,
+            threshold,
+            timelock,
+        },
+    )
+}
+
+pub fn propose_withdrawal_ix(
+    multisig: &Pubkey,
+    index: u64,
+    proposer: &Pubkey,
+    mint: &Pubkey,
+    receiver: &Pubkey,
+) -> Instruction {
+    let accounts = pnft_transfer::accounts::ProposeWithdrawal {
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        proposer: *proposer,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::ProposeWithdrawal {
+            item: *mint,
+            receiver: *receiver,
+        },
+    )
+}
+
+pub fn approve_ix(multisig: &Pubkey, index: u64, approver: &Pubkey) -> Instruction {
+    let accounts = pnft_transfer::accounts::Approve {
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        approver: *approver,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::Approve {},
+    )
+}
+
+pub fn execute_withdrawal_ix(
+    multisig: &Pubkey,
+    index: u64,
+    proposer: &Pubkey,
+    mint: &Pubkey,
+    receiver: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    let vault = pda::find_vault(multisig).0;
+    let vault_item_token = get_associated_token_address(&vault, mint);
+    let receiver_item_token = get_associated_token_address(receiver, mint);
+    let accounts = pnft_transfer::accounts::ExecuteWithdrawal {
+        item: *mint,
+        multisig: *multisig,
+        proposal: pda::find_proposal(multisig, index).0,
+        vault,
+        vault_item_token,
+        proposer: *proposer,
+        receiver: *receiver,
+        receiver_item_token,
+        payer: *payer,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        vault_token_record: pda::find_token_record(mint, &vault_item_token).0,
+        receiver_token_record: pda::find_token_record(mint, &receiver_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::ExecuteWithdrawal {
+            rules_acc_present: false,
+        },
+    )
+}
+
+// a listing as the baseline program left it: seeded [item, "listings"], with only bump, item and
+// item_token after Listing's discriminator, and the item escrowed in its ATA
+pub async fn create_legacy_listing(ctx: &mut ProgramTestContext, seller: &Keypair) -> Pubkey {
+    let mint = mint_pnft(ctx, &seller.pubkey(), None).await;
+    let (legacy_listing, bump) = pda::find_legacy_listing(&mint);
+    escrow_in_legacy_listing(ctx, seller, &mint, &legacy_listing, bump, &[]).await;
+    mint
+}
+
+// one of the listings that came next: seeded [item, seller, "listings"], the same fields then
+// seller, price and quantity
+pub async fn create_legacy_seller_listing(
+    ctx: &mut ProgramTestContext,
+    seller: &Keypair,
+    price: u64,
+) -> Pubkey {
+    let mint = mint_pnft(ctx, &seller.pubkey(), None).await;
+    let (legacy_listing, bump) = pda::find_legacy_seller_listing(&mint, &seller.pubkey());
+    let mut fields = seller.pubkey().to_bytes().to_vec();
+    fields.extend_from_slice(&price.to_le_bytes());
+    fields.extend_from_slice(&1u64.to_le_bytes());
+    escrow_in_legacy_listing(ctx, seller, &mint, &legacy_listing, bump, &fields).await;
+    mint
+}
+
+// moves the seller's item to the listing's ATA and writes the listing: discriminator, bump, item,
+// item_token, then `fields`, zero-padded to LEGACY_LISTING_SIZE
+async fn escrow_in_legacy_listing(
+    ctx: &mut ProgramTestContext,
+    seller: &Keypair,
+    mint: &Pubkey,
+    legacy_listing: &Pubkey,
+    bump: u8,
+    fields: &[u8],
+) {
+    let listing_item_token = create_ata(ctx, legacy_listing, mint).await;
+    let ix = transfer_ix(mint, &seller.pubkey(), legacy_listing, None);
+    process(ctx, &[ix], &[seller]).await.unwrap();
+
+    let mut data = <Listing as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+    data.push(bump);
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(listing_item_token.as_ref());
+    data.extend_from_slice(fields);
+    data.resize(LEGACY_LISTING_SIZE, 0);
+    let account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: pnft_transfer::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    ctx.set_account(legacy_listing, &account.into());
+}
+
+// a listing as it was written once nonce joined the seeds, before collection was inserted after
+// seller: the fields up to quantity, zero-padded to LEGACY_LISTING_SIZE. Only the account,
+// nothing escrowed
+pub fn pre_collection_listing(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+    price: u64,
+    quantity: u64,
+) -> (Pubkey, Account) {
+    let (listing, bump) = pda::find_listing(mint, seller, nonce);
+    let mut data = <Listing as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+    data.push(bump);
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(get_associated_token_address(&listing, mint).as_ref());
+    data.extend_from_slice(seller.as_ref());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&quantity.to_le_bytes());
+    data.resize(LEGACY_LISTING_SIZE, 0);
+    let account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: pnft_transfer::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    (listing, account)
+}
+
+// a listing as it was written before the version byte: seeded by item + seller + nonce, the
+// fields up to quantity and nothing after. Only the account, nothing escrowed
+pub fn pre_version_listing(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+    price: u64,
+    quantity: u64,
+) -> (Pubkey, Account) {
+    let (listing, bump) = pda::find_listing(mint, seller, nonce);
+    let mut data = <Listing as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+    data.push(bump);
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(get_associated_token_address(&listing, mint).as_ref());
+    data.extend_from_slice(seller.as_ref());
+    data.extend_from_slice(Pubkey::default().as_ref());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&quantity.to_le_bytes());
+    assert_eq!(data.len(), PRE_VERSION_LISTING_SIZE);
+    let account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: pnft_transfer::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    (listing, account)
+}
+
+// a version 1 listing: the pre-version fields, then version 1 and the zeroed reserved bytes.
+// Only the account, nothing escrowed
+pub fn v1_listing(
+    mint: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+    price: u64,
+    quantity: u64,
+) -> (Pubkey, Account) {
+    let (listing, mut account) = pre_version_listing(mint, seller, nonce, price, quantity);
+    account.data.push(1);
+    account.data.extend_from_slice(&[0; pnft_transfer::LISTING_RESERVED_SIZE]);
+    assert_eq!(account.data.len(), pnft_transfer::LISTING_V1_SIZE);
+    account.lamports = Rent::default().minimum_balance(account.data.len());
+    (listing, account)
+}
+
+pub fn migrate_listing_ix(listing: &Pubkey, mint: &Pubkey, payer: &Pubkey) -> Instruction {
+    let accounts = pnft_transfer::accounts::MigrateListing {
+        listing: *listing,
+        item: *mint,
+        item_metadata: pda::find_metadata(mint).0,
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::MigrateListing {},
+    )
+}
+
+pub fn unwind_legacy_listing_ix(
+    legacy_listing: &Pubkey,
+    mint: &Pubkey,
+    seller: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    let legacy_listing = *legacy_listing;
+    let listing_item_token = get_associated_token_address(&legacy_listing, mint);
+    let seller_item_token = get_associated_token_address(seller, mint);
+    let accounts = pnft_transfer::accounts::UnwindLegacyListing {
+        item: *mint,
+        legacy_listing,
+        listing_item_token,
+        seller: *seller,
+        seller_item_token,
+        authority: *authority,
+        program: pnft_transfer::ID,
+        program_data: pda::find_program_data().0,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+        associated_token_program: associated_token::ID,
+        item_metadata: pda::find_metadata(mint).0,
+        edition: pda::find_edition(mint).0,
+        listing_token_record: pda::find_token_record(mint, &listing_item_token).0,
+        seller_token_record: pda::find_token_record(mint, &seller_item_token).0,
+        token_metadata_program: mpl_token_metadata::ID,
+        instructions: sysvar::instructions::ID,
+        authorization_rules_program: mpl_token_auth_rules::ID,
+    };
+    ix(
+        accounts.to_account_metas(None),
+        vec![],
+        pnft_transfer::instruction::UnwindLegacyListing {
+            rules_acc_present: false,
+        },
+    )
+}
+
+// rewrites an existing account in place, for faking states the programs won't produce
+pub async fn edit_account(
+    ctx: &mut ProgramTestContext,
+    key: &Pubkey,
+    edit: impl FnOnce(&mut Account),
+) {
+    let mut account = ctx.banks_client.get_account(*key).await.unwrap().unwrap();
+    edit(&mut account);
+    ctx.set_account(key, &account.into());
+}
+
+pub async fn fetch_listing(ctx: &mut ProgramTestContext, listing: &Pubkey) -> Option<Listing> {
+    let account = ctx.banks_client.get_account(*listing).await.unwrap()?;
+    Some(Listing::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_stake_record(
+    ctx: &mut ProgramTestContext,
+    stake_record: &Pubkey,
+) -> Option<StakeRecord> {
+    let account = ctx.banks_client.get_account(*stake_record).await.unwrap()?;
+    Some(StakeRecord::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_loan(ctx: &mut ProgramTestContext, loan: &Pubkey) -> Option<Loan> {
+    let account = ctx.banks_client.get_account(*loan).await.unwrap()?;
+    Some(Loan::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_rental(ctx: &mut ProgramTestContext, rental: &Pubkey) -> Option<Rental> {
+    let account = ctx.banks_client.get_account(*rental).await.unwrap()?;
+    Some(Rental::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_bundle(ctx: &mut ProgramTestContext, bundle: &Pubkey) -> Option<Bundle> {
+    let account = ctx.banks_client.get_account(*bundle).await.unwrap()?;
+    Some(Bundle::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_vault(ctx: &mut ProgramTestContext, vault: &Pubkey) -> Option<Vault> {
+    let account = ctx.banks_client.get_account(*vault).await.unwrap()?;
+    Some(Vault::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_multisig(ctx: &mut ProgramTestContext, multisig: &Pubkey) -> Option<Multisig> {
+    let account = ctx.banks_client.get_account(*multisig).await.unwrap()?;
+    Some(Multisig::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+pub async fn fetch_proposal(ctx: &mut ProgramTestContext, proposal: &Pubkey) -> Option<Proposal> {
+    let account = ctx.banks_client.get_account(*proposal).await.unwrap()?;
+    Some(Proposal::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
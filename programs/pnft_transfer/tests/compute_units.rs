@@ -0,0 +1,297 @@
+// Compute units used by transfer_pnft, list_pnft and buy_pnft with no ruleset, a Pass ruleset
+// and a ruleset that needs a merkle proof. Writes a table to target/compute_units.md and fails
+// when an instruction goes over the 200k default limit or over its baseline + CU_TOLERANCE_PCT.
+//
+//   cargo test-sbf --test compute_units                             -> check against the baseline
+//   CU_UPDATE_BASELINE=1 cargo test-sbf --test compute_units        -> rewrite the baseline
+//
+// Natively pnft_transfer itself isn't metered (just the metaplex CPIs), so the counts are a lower
+// bound: the 200k limit is still checked, the baseline only under test-sbf. There a missing
+// baseline, or one without an entry for every instruction + case, fails the test until it's
+// regenerated.
+mod common;
+
+use anchor_lang::InstructionData;
+use common::*;
+use pnft_transfer::{AuthorizationDataLocal, PayloadTypeLocal, ProofInfoLocal, TaggedPayload};
+use solana_sdk::keccak;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+const DEFAULT_COMPUTE_LIMIT: u64 = 200_000;
+const DEFAULT_TOLERANCE_PCT: u64 = 5;
+const MERKLE_LEAVES: usize = 8;
+
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compute_units.baseline");
+const REPORT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/compute_units.md");
+
+const INSTRUCTIONS: [&str; 3] = ["transfer_pnft", "list_pnft", "buy_pnft"];
+
+#[derive(Clone, Copy)]
+enum Case {
+    NoRuleset,
+    Ruleset,
+    MerkleProof,
+}
+
+impl Case {
+    fn name(&self) -> &'static str {
+        match self {
+            Case::NoRuleset => "no_ruleset",
+            Case::Ruleset => "ruleset",
+            Case::MerkleProof => "merkle_proof",
+        }
+    }
+}
+
+fn sbf() -> bool {
+    std::env::var("SBF_OUT_DIR").is_ok() || std::env::var("BPF_OUT_DIR").is_ok()
+}
+
+// same hashing as mpl_token_auth_rules::utils::compute_merkle_root
+fn merkle_parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (l, r) = if a <= b { (a, b) } else { (b, a) };
+    keccak::hashv(&[&[0x01], l, r]).0
+}
+
+// root of a tree with the holders + padding leaves, and each holder's proof
+fn merkle_tree(holders: &[Pubkey]) -> ([u8; 32], Vec<Vec<[u8; 32]>>) {
+    let mut level: Vec<[u8; 32]> = holders.iter().map(|h| h.to_bytes()).collect();
+    level.resize_with(MERKLE_LEAVES, || Pubkey::new_unique().to_bytes());
+    let mut indexes: Vec<usize> = (0..holders.len()).collect();
+    let mut proofs = vec![vec![]; holders.len()];
+    while level.len() > 1 {
+        for (proof, index) in proofs.iter_mut().zip(indexes.iter_mut()) {
+            proof.push(level[*index ^ 1]);
+            *index /= 2;
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| merkle_parent(&pair[0], &pair[1]))
+            .collect();
+    }
+    (level[0], proofs)
+}
+
+// proof for the holder being transferred from, buy_pnft's listing PDA passes through its seeds
+fn merkle_rule(root: [u8; 32]) -> Rule {
+    Rule::Any {
+        rules: vec![
+            Rule::PubkeyTreeMatch {
+                root,
+                pubkey_field: "Source".to_string(),
+                proof_field: "SourceProof".to_string(),
+            },
+            Rule::PDAMatch {
+                program: Some(pnft_transfer::ID),
+                pda_field: "Source".to_string(),
+                seeds_field: "SourceSeeds".to_string(),
+            },
+        ],
+    }
+}
+
+fn source_proof(proof: Vec<[u8; 32]>) -> AuthorizationDataLocal {
+    AuthorizationDataLocal {
+        payload: vec![TaggedPayload {
+            name: "SourceProof".to_string(),
+            payload: PayloadTypeLocal::MerkleProof(ProofInfoLocal { proof }),
+        }],
+    }
+}
+
+async fn units(ctx: &mut ProgramTestContext, ix: &Instruction, signer: &Keypair) -> u64 {
+    let simulation = simulate(ctx, std::slice::from_ref(ix), &[signer]).await;
+    if let Err(e) = simulation.result {
+        panic!("{:?}\n{:#?}", e, simulation.logs);
+    }
+    process(ctx, std::slice::from_ref(ix), &[signer])
+        .await
+        .unwrap();
+    simulation.units_consumed
+}
+
+// owner -transfer-> seller -list-> listing -buy-> buyer
+async fn measure(case: Case) -> [u64; 3] {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+
+    let (rule_set, transfer_auth, list_auth) = match case {
+        Case::NoRuleset => (None, None, None),
+        Case::Ruleset => (
+            Some(create_rule_set(&mut ctx, Rule::Pass).await),
+            None,
+            None,
+        ),
+        Case::MerkleProof => {
+            let (root, mut proofs) = merkle_tree(&[owner.pubkey(), seller.pubkey()]);
+            let seller_proof = proofs.pop().unwrap();
+            let owner_proof = proofs.pop().unwrap();
+            (
+                Some(create_rule_set(&mut ctx, merkle_rule(root)).await),
+                Some(source_proof(owner_proof)),
+                Some(source_proof(seller_proof)),
+            )
+        }
+    };
+
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), rule_set).await;
+    create_ata(&mut ctx, &seller.pubkey(), &mint).await;
+    create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+
+    let mut transfer = transfer_ix(&mint, &owner.pubkey(), &seller.pubkey(), rule_set);
+    transfer.data = pnft_transfer::instruction::TransferPnft {
+        authorization_data: transfer_auth,
+        rules_acc_present: rule_set.is_some(),
+        memo: None,
+    }
+    .data();
+
+    let price = 100_000_000;
+    let mut list = list_ix(&mint, &seller.pubkey(), price, 0, rule_set);
+    list.data = pnft_transfer::instruction::ListPnft {
+        price,
+        nonce: 0,
+        authorization_data: list_auth,
+        rules_acc_present: rule_set.is_some(),
+    }
+    .data();
+
+    let buy = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, price, rule_set);
+
+    [
+        units(&mut ctx, &transfer, &owner).await,
+        units(&mut ctx, &list, &seller).await,
+        units(&mut ctx, &buy, &buyer).await,
+    ]
+}
+
+// "<instruction> <case> <units>" per line
+fn read_baseline() -> Option<BTreeMap<(String, String), u64>> {
+    let baseline = std::fs::read_to_string(BASELINE).ok()?;
+    let baseline = baseline
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let parts: Vec<&str> = l.split_whitespace().collect();
+            assert_eq!(parts.len(), 3, "bad baseline line: {}", l);
+            (
+                (parts[0].to_string(), parts[1].to_string()),
+                parts[2].parse().unwrap(),
+            )
+        })
+        .collect();
+    Some(baseline)
+}
+
+fn write_baseline(measured: &BTreeMap<(String, String), u64>) {
+    let mut out =
+        String::from("# written by CU_UPDATE_BASELINE=1 cargo test-sbf --test compute_units\n");
+    for ((ix, case), units) in measured {
+        writeln!(out, "{} {} {}", ix, case, units).unwrap();
+    }
+    std::fs::write(BASELINE, out).unwrap();
+}
+
+#[tokio::test]
+async fn compute_units_stay_within_budget() {
+    let cases = [Case::NoRuleset, Case::Ruleset, Case::MerkleProof];
+    let mut measured = BTreeMap::new();
+    for case in cases {
+        let units = measure(case).await;
+        for (ix, units) in INSTRUCTIONS.iter().zip(units) {
+            measured.insert((ix.to_string(), case.name().to_string()), units);
+        }
+    }
+
+    let update = std::env::var("CU_UPDATE_BASELINE").is_ok();
+    let baseline = match read_baseline() {
+        Some(baseline) => baseline,
+        None if sbf() && !update => panic!(
+            "no compute unit baseline at {}, generate it with \
+             CU_UPDATE_BASELINE=1 cargo test-sbf --test compute_units and commit it",
+            BASELINE
+        ),
+        None => {
+            eprintln!(
+                "WARNING: no compute unit baseline at {}, nothing to compare against",
+                BASELINE
+            );
+            BTreeMap::new()
+        }
+    };
+    let tolerance = std::env::var("CU_TOLERANCE_PCT")
+        .map(|t| t.parse().unwrap())
+        .unwrap_or(DEFAULT_TOLERANCE_PCT);
+
+    let mut report = String::new();
+    writeln!(
+        report,
+        "CUs per txn (compute budget ix included), {}\n",
+        if sbf() {
+            "sbf"
+        } else {
+            "native, pnft_transfer not metered"
+        }
+    )
+    .unwrap();
+    writeln!(
+        report,
+        "| instruction | no ruleset | ruleset | merkle proof |"
+    )
+    .unwrap();
+    writeln!(report, "|---|---|---|---|").unwrap();
+    for ix in INSTRUCTIONS {
+        let row: Vec<String> = cases
+            .iter()
+            .map(|case| {
+                let key = (ix.to_string(), case.name().to_string());
+                match baseline.get(&key) {
+                    Some(base) => format!("{} (baseline {})", measured[&key], base),
+                    None => measured[&key].to_string(),
+                }
+            })
+            .collect();
+        writeln!(report, "| {} | {} |", ix, row.join(" | ")).unwrap();
+    }
+    std::fs::create_dir_all(std::path::Path::new(REPORT).parent().unwrap()).unwrap();
+    std::fs::write(REPORT, &report).unwrap();
+    println!("{}", report);
+
+    if sbf() && update {
+        write_baseline(&measured);
+        return;
+    }
+
+    let mut regressions = vec![];
+    for ((ix, case), units) in &measured {
+        if *units > DEFAULT_COMPUTE_LIMIT {
+            regressions.push(format!(
+                "{} {}: {} over the {} default limit",
+                ix, case, units, DEFAULT_COMPUTE_LIMIT
+            ));
+        }
+        if !sbf() {
+            continue;
+        }
+        match baseline.get(&(ix.clone(), case.clone())) {
+            Some(base) => {
+                let allowed = base + base * tolerance / 100;
+                if *units > allowed {
+                    regressions.push(format!(
+                        "{} {}: {} vs baseline {} (+{}% allowed)",
+                        ix, case, units, base, tolerance
+                    ));
+                }
+            }
+            None => regressions.push(format!("{} {}: not in the baseline", ix, case)),
+        }
+    }
+    assert!(
+        regressions.is_empty(),
+        "compute regressions:\n{}",
+        regressions.join("\n")
+    );
+}
@@ -0,0 +1,277 @@
+// Throws swapped / foreign / missing accounts and random authorization payloads at transfer_pnft.
+// Whatever goes in, the program has to either fail (never panic) or move the item exactly from
+// the signing owner to the receiver it was given.
+//
+//   FUZZ_ITERS=2000 FUZZ_SEED=42 cargo test-sbf --test fuzz_accounts
+//
+// The host side of the payload conversion is covered by fuzz/ (cargo fuzz).
+mod common;
+
+use anchor_lang::InstructionData;
+use common::*;
+use pnft_transfer::{
+    AuthorizationDataLocal, PayloadTypeLocal, ProofInfoLocal, SeedsVecLocal, TaggedPayload,
+};
+use solana_sdk::instruction::AccountMeta;
+
+const WALLETS: usize = 4;
+
+const PAYLOAD_NAMES: &[&str] = &[
+    "Amount",
+    "Authority",
+    "AuthoritySeeds",
+    "Delegate",
+    "DelegateSeeds",
+    "Destination",
+    "DestinationSeeds",
+    "Holder",
+    "Source",
+    "SourceSeeds",
+];
+
+// xorshift64*, good enough to shuffle accounts and reproducible from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn bool(&mut self) -> bool {
+        self.next() & 1 == 1
+    }
+
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        (0..self.below(max_len + 1))
+            .map(|_| self.next() as u8)
+            .collect()
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .map(|v| {
+            v.parse()
+                .unwrap_or_else(|_| panic!("{} must be a number", name))
+        })
+        .unwrap_or(default)
+}
+
+fn random_authorization_data(rng: &mut Rng, pool: &[Pubkey]) -> AuthorizationDataLocal {
+    let payload = (0..rng.below(6))
+        .map(|_| {
+            let name = if rng.below(4) == 0 {
+                String::from_utf8_lossy(&rng.bytes(12)).into_owned()
+            } else {
+                rng.pick(PAYLOAD_NAMES).to_string()
+            };
+            let payload = match rng.below(4) {
+                0 => PayloadTypeLocal::Pubkey(rng.pick(pool)),
+                1 => PayloadTypeLocal::Seeds(SeedsVecLocal {
+                    seeds: (0..rng.below(20)).map(|_| rng.bytes(40)).collect(),
+                }),
+                2 => PayloadTypeLocal::MerkleProof(ProofInfoLocal {
+                    proof: (0..rng.below(10))
+                        .map(|_| {
+                            let mut leaf = [0u8; 32];
+                            leaf.iter_mut().for_each(|b| *b = rng.next() as u8);
+                            leaf
+                        })
+                        .collect(),
+                }),
+                _ => PayloadTypeLocal::Number(rng.next()),
+            };
+            TaggedPayload { name, payload }
+        })
+        .collect();
+    AuthorizationDataLocal { payload }
+}
+
+// TransferPNFT's own accounts, anything after these is remaining accounts
+fn named_accounts() -> usize {
+    transfer_ix(
+        &Pubkey::default(),
+        &Pubkey::default(),
+        &Pubkey::default(),
+        None,
+    )
+    .accounts
+    .len()
+}
+
+fn mutate(ix: &mut Instruction, rng: &mut Rng, pool: &[Pubkey], wallets: &[Pubkey]) {
+    let len = ix.accounts.len();
+    match rng.below(7) {
+        // swap two accounts, the owner stays put so the txn can still be signed
+        0 => {
+            let (a, b) = (1 + rng.below(len - 1), 1 + rng.below(len - 1));
+            let tmp = ix.accounts[a].pubkey;
+            ix.accounts[a].pubkey = ix.accounts[b].pubkey;
+            ix.accounts[b].pubkey = tmp;
+        }
+        // some other account in a slot
+        1 => ix.accounts[1 + rng.below(len - 1)].pubkey = rng.pick(pool),
+        // somebody else signs as the owner
+        2 => ix.accounts[0].pubkey = rng.pick(wallets),
+        // extra / missing remaining accounts
+        3 => {
+            if len > named_accounts() && rng.bool() {
+                ix.accounts.pop();
+            } else {
+                ix.accounts
+                    .push(AccountMeta::new_readonly(rng.pick(pool), false));
+            }
+        }
+        // well formed but random authorization data
+        4 => {
+            ix.data = pnft_transfer::instruction::TransferPnft {
+                authorization_data: Some(random_authorization_data(rng, pool)),
+                rules_acc_present: rng.bool(),
+                memo: None,
+            }
+            .data();
+        }
+        // garbage after the discriminator
+        5 => {
+            ix.data.truncate(8);
+            ix.data.extend(rng.bytes(64));
+        }
+        // lie about the ruleset being passed, it's just before the (None) memo
+        _ => {
+            let at = ix.data.len().saturating_sub(2);
+            if let Some(rules_acc_present) = ix.data.get_mut(at) {
+                *rules_acc_present ^= 1;
+            }
+        }
+    }
+}
+
+// which wallet's ATA holds the item, also checks it's in exactly one of them
+async fn holder(ctx: &mut ProgramTestContext, wallets: &[Keypair], mint: &Pubkey) -> usize {
+    let mut holders = vec![];
+    for (i, wallet) in wallets.iter().enumerate() {
+        let ata = get_associated_token_address(&wallet.pubkey(), mint);
+        match token_balance(ctx, &ata).await {
+            Some(0) | None => {}
+            Some(1) => holders.push(i),
+            Some(n) => panic!("{} holds {} of a pnft", wallet.pubkey(), n),
+        }
+    }
+    assert_eq!(holders.len(), 1, "item {} held by {:?}", mint, holders);
+    holders[0]
+}
+
+#[tokio::test]
+async fn transfer_pnft_survives_account_and_payload_fuzzing() {
+    let iters = env_or("FUZZ_ITERS", 100);
+    let seed = env_or("FUZZ_SEED", 0x5eed);
+    println!(
+        "fuzzing transfer_pnft, FUZZ_ITERS={} FUZZ_SEED={}",
+        iters, seed
+    );
+    let mut rng = Rng(seed.max(1));
+
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let mut wallets = vec![];
+    for _ in 0..WALLETS {
+        wallets.push(funded_wallet(&mut ctx, 1_000_000_000).await);
+    }
+    let wallet_keys: Vec<Pubkey> = wallets.iter().map(|w| w.pubkey()).collect();
+
+    let mut items = vec![];
+    for item_rule_set in [None, Some(rule_set)] {
+        let mint = mint_pnft(&mut ctx, &wallet_keys[0], item_rule_set).await;
+        for wallet in &wallet_keys[1..] {
+            create_ata(&mut ctx, wallet, &mint).await;
+        }
+        items.push((mint, item_rule_set));
+    }
+
+    // everything a confused or malicious client could plausibly put in a slot
+    let mut pool = wallet_keys.clone();
+    for (mint, _) in &items {
+        pool.extend([*mint, pda::find_metadata(mint).0, pda::find_edition(mint).0]);
+        for wallet in &wallet_keys {
+            let ata = get_associated_token_address(wallet, mint);
+            pool.extend([ata, pda::find_token_record(mint, &ata).0]);
+        }
+    }
+    pool.extend([
+        rule_set,
+        token::ID,
+        system_program::ID,
+        associated_token::ID,
+        mpl_token_metadata::ID,
+        mpl_token_auth_rules::ID,
+        pnft_transfer::ID,
+        sysvar::rent::ID,
+        sysvar::instructions::ID,
+        Pubkey::new_unique(),
+    ]);
+
+    let mut holders = vec![0; items.len()];
+    let mut moved = 0;
+    for i in 0..iters {
+        let item = rng.below(items.len());
+        let (mint, item_rule_set) = items[item];
+        let from = wallet_keys[holders[item]];
+        let to = rng.pick(&wallet_keys);
+
+        let mut ix = transfer_ix(&mint, &from, &to, item_rule_set);
+        for _ in 0..rng.below(4) {
+            mutate(&mut ix, &mut rng, &pool, &wallet_keys);
+        }
+        let owner = ix.accounts[0].pubkey;
+        let receiver = ix.accounts[1].pubkey;
+        let signer = wallets.iter().find(|w| w.pubkey() == owner).unwrap();
+
+        let simulation = simulate(&mut ctx, &[ix.clone()], &[signer]).await;
+        assert!(
+            !simulation.logs.iter().any(|log| log.contains("panicked")),
+            "iteration {}: panicked on {:?}\n{:#?}",
+            i,
+            ix,
+            simulation.logs
+        );
+
+        let result = process(&mut ctx, &[ix.clone()], &[signer]).await;
+        for (j, (mint, _)) in items.iter().enumerate() {
+            let new_holder = holder(&mut ctx, &wallets, mint).await;
+            if new_holder == holders[j] {
+                continue;
+            }
+            assert!(
+                result.is_ok(),
+                "iteration {}: item moved by a failed txn",
+                i
+            );
+            assert_eq!(j, item, "iteration {}: moved an item it wasn't asked to", i);
+            assert_eq!(
+                from, owner,
+                "iteration {}: moved without the holder signing",
+                i
+            );
+            assert_eq!(
+                wallet_keys[new_holder], receiver,
+                "iteration {}: item went to {} instead of the receiver {}",
+                i, wallet_keys[new_holder], receiver
+            );
+            holders[j] = new_holder;
+            moved += 1;
+        }
+    }
+    println!("{} iterations, {} went through", iters, moved);
+}
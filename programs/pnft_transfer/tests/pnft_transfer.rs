@@ -0,0 +1,1525 @@
+// Runs the program against the Token Metadata / Token Auth Rules builds bundled in
+// tests/programs, fully offline.
+//
+//   cargo test-sbf   -> runs the built pnft_transfer.so
+//   cargo test       -> runs pnft_transfer natively (faster, same CPIs into the metaplex .so's)
+mod common;
+
+use common::*;
+// ----------------------------------------------------------------------------- transfer
+
+#[tokio::test]
+async fn transfers_pnft_without_ruleset() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let src = get_associated_token_address(&owner.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &src).await, Some(0));
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+}
+
+#[tokio::test]
+async fn transfers_pnft_with_memo() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = transfer_with_memo_ix(&mint, &owner.pubkey(), &receiver.pubkey(), &"x".repeat(129));
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::MemoTooLong));
+
+    let ix = transfer_with_memo_ix(&mint, &owner.pubkey(), &receiver.pubkey(), "order-1234");
+    let simulation = simulate(&mut ctx, std::slice::from_ref(&ix), &[&owner]).await;
+    assert!(simulation.result.is_ok());
+    assert!(simulation.logs.iter().any(|log| log.contains("order-1234")));
+
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+}
+
+#[tokio::test]
+async fn transfers_pnft_with_sponsored_rent() {
+    let mut ctx = setup().await;
+    // owner only signs, it never holds any SOL
+    let owner = Keypair::new();
+    let relayer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+    let relayer_before = lamports(&mut ctx, &relayer.pubkey()).await;
+
+    // the relayer pays for the dest token record
+    let ix = sponsored_transfer_ix(&mint, &owner.pubkey(), &relayer.pubkey(), &receiver.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner, &relayer]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+    assert_eq!(lamports(&mut ctx, &owner.pubkey()).await, 0);
+    assert!(lamports(&mut ctx, &relayer.pubkey()).await < relayer_before);
+}
+
+#[tokio::test]
+async fn transfers_pnft_with_sponsored_rent_and_ruleset() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let owner = Keypair::new();
+    let relayer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), Some(rule_set)).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    // the payer goes after the (positional) ruleset
+    let ix = sponsored_transfer_ix(
+        &mint,
+        &owner.pubkey(),
+        &relayer.pubkey(),
+        &receiver.pubkey(),
+        Some(rule_set),
+    );
+    process(&mut ctx, &[ix], &[&owner, &relayer]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+    assert_eq!(lamports(&mut ctx, &owner.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn transfers_pnft_with_memo_and_sponsored_rent() {
+    let mut ctx = setup().await;
+    let owner = Keypair::new();
+    let relayer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    // the payer slot comes after the memo program
+    let mut ix = transfer_with_memo_ix(&mint, &owner.pubkey(), &receiver.pubkey(), "order 42");
+    ix.accounts
+        .push(anchor_lang::prelude::AccountMeta::new(relayer.pubkey(), true));
+    process(&mut ctx, &[ix], &[&owner, &relayer]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+    assert_eq!(lamports(&mut ctx, &owner.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn transfer_fails_with_unsigned_payer() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let mut ix = sponsored_transfer_ix(
+        &mint,
+        &owner.pubkey(),
+        &Pubkey::new_unique(),
+        &receiver.pubkey(),
+        None,
+    );
+    ix.accounts.last_mut().unwrap().is_signer = false;
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadPayer));
+}
+
+#[tokio::test]
+async fn transfers_pnft_with_ruleset() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), Some(rule_set)).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), Some(rule_set));
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(1));
+}
+
+#[tokio::test]
+async fn transfer_fails_without_ruleset_account() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), Some(rule_set)).await;
+    create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), None);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadRuleset));
+}
+
+#[tokio::test]
+async fn transfer_fails_when_ruleset_denies() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(
+        &mut ctx,
+        Rule::Not {
+            rule: Box::new(Rule::Pass),
+        },
+    )
+    .await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), Some(rule_set)).await;
+    let dest = create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), Some(rule_set));
+    assert!(process(&mut ctx, &[ix], &[&owner]).await.is_err());
+    assert_eq!(token_balance(&mut ctx, &dest).await, Some(0));
+}
+
+#[tokio::test]
+async fn transfer_fails_for_non_owner() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let thief = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    create_ata(&mut ctx, &thief.pubkey(), &mint).await;
+
+    // thief signs as the owner of someone else's ATA
+    let mut ix = transfer_ix(&mint, &thief.pubkey(), &thief.pubkey(), None);
+    let src = get_associated_token_address(&owner.pubkey(), &mint);
+    ix.accounts[2].pubkey = src;
+    ix.accounts[11].pubkey = pda::find_token_record(&mint, &src).0;
+    assert!(process(&mut ctx, &[ix], &[&thief]).await.is_err());
+    assert_eq!(token_balance(&mut ctx, &src).await, Some(1));
+}
+
+// ----------------------------------------------------------------------------- list & buy
+
+async fn lists_and_buys(rule_set: Option<Pubkey>, mut ctx: ProgramTestContext) {
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), rule_set).await;
+    let price = 100_000_000;
+
+    let ix = list_ix(&mint, &seller.pubkey(), price, 0, rule_set);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    let listing = pda::find_listing(&mint, &seller.pubkey(), 0).0;
+    let listing_item_token = get_associated_token_address(&listing, &mint);
+    let state = fetch_listing(&mut ctx, &listing).await.unwrap();
+    assert_eq!(state.item, mint);
+    assert_eq!(state.seller, seller.pubkey());
+    assert_eq!(state.price, price);
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, Some(1));
+
+    let buyer_item_token = create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+    let seller_before = lamports(&mut ctx, &seller.pubkey()).await;
+
+    let ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, price, rule_set);
+    process(&mut ctx, &[ix], &[&buyer]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &buyer_item_token).await, Some(1));
+    // price + the listing's and escrow's rent
+    assert!(lamports(&mut ctx, &seller.pubkey()).await > seller_before + price);
+    assert!(fetch_listing(&mut ctx, &listing).await.is_none());
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, None);
+}
+
+#[tokio::test]
+async fn lists_and_buys_without_ruleset() {
+    let ctx = setup().await;
+    lists_and_buys(None, ctx).await;
+}
+
+#[tokio::test]
+async fn lists_and_buys_with_ruleset() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    lists_and_buys(Some(rule_set), ctx).await;
+}
+
+#[tokio::test]
+async fn lists_and_cancels() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let seller_item_token = get_associated_token_address(&seller.pubkey(), &mint);
+
+    let ix = list_ix(&mint, &seller.pubkey(), 100_000_000, 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    let listing = pda::find_listing(&mint, &seller.pubkey(), 0).0;
+    let listing_item_token = get_associated_token_address(&listing, &mint);
+    let listing_rent = lamports(&mut ctx, &listing).await;
+    let escrow_rent = lamports(&mut ctx, &listing_item_token).await;
+    let seller_before = lamports(&mut ctx, &seller.pubkey()).await;
+
+    let ix = cancel_listing_ix(&mint, &seller.pubkey(), 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(1));
+    assert!(fetch_listing(&mut ctx, &listing).await.is_none());
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, None);
+    // both the listing's and the escrow's rent
+    assert_eq!(
+        lamports(&mut ctx, &seller.pubkey()).await,
+        seller_before + listing_rent + escrow_rent
+    );
+}
+
+#[tokio::test]
+async fn list_fails_for_non_programmable_item() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_asset(&mut ctx, &seller.pubkey(), TokenStandard::NonFungible, None, None).await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotProgrammable));
+}
+
+#[tokio::test]
+async fn list_fails_for_fake_edition() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let other_mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let edition = pda::find_edition(&mint).0;
+
+    // somebody else's (real) master edition
+    let mut ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == edition {
+            meta.pubkey = pda::find_edition(&other_mint).0;
+        }
+    }
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(
+        result,
+        anchor_err(anchor_lang::error::ErrorCode::ConstraintAddress),
+    );
+
+    // the right address, but a print rather than a master edition
+    edit_account(&mut ctx, &edition, |account| {
+        account.data[0] = mpl_token_metadata::state::Key::EditionV1 as u8;
+    })
+    .await;
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+
+    // master edition bytes in an account Token Metadata doesn't own
+    edit_account(&mut ctx, &edition, |account| {
+        account.data[0] = mpl_token_metadata::state::Key::MasterEditionV2 as u8;
+        account.owner = system_program::ID;
+    })
+    .await;
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+}
+
+#[tokio::test]
+async fn list_fails_for_printable_master_edition() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_asset_with_print_supply(
+        &mut ctx,
+        &seller.pubkey(),
+        TokenStandard::ProgrammableNonFungible,
+        None,
+        None,
+        PrintSupply::Limited(5),
+    )
+    .await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+}
+
+#[tokio::test]
+async fn list_fails_when_edition_isnt_mint_authority() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let impostor = Pubkey::new_unique();
+
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    edit_account(&mut ctx, &mint, |account| {
+        let mut state = spl_token::state::Mint::unpack(&account.data).unwrap();
+        state.mint_authority = Some(impostor).into();
+        spl_token::state::Mint::pack(state, &mut account.data).unwrap();
+    })
+    .await;
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    edit_account(&mut ctx, &mint, |account| {
+        let mut state = spl_token::state::Mint::unpack(&account.data).unwrap();
+        state.freeze_authority = Some(impostor).into();
+        spl_token::state::Mint::pack(state, &mut account.data).unwrap();
+    })
+    .await;
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+}
+
+#[tokio::test]
+async fn list_fails_for_non_owner() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let thief = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+
+    let mut ix = list_ix(&mint, &thief.pubkey(), 1, 0, None);
+    let thief_src = get_associated_token_address(&thief.pubkey(), &mint);
+    let thief_token_record = pda::find_token_record(&mint, &thief_src).0;
+    let src = get_associated_token_address(&owner.pubkey(), &mint);
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == thief_src {
+            meta.pubkey = src;
+        } else if meta.pubkey == thief_token_record {
+            meta.pubkey = pda::find_token_record(&mint, &src).0;
+        }
+    }
+    let result = process(&mut ctx, &[ix], &[&thief]).await;
+    assert_custom_err(
+        result,
+        anchor_err(anchor_lang::error::ErrorCode::ConstraintTokenOwner),
+    );
+}
+
+#[tokio::test]
+async fn list_fails_with_wrong_ruleset() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), Some(rule_set)).await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, Some(Pubkey::new_unique()));
+    assert!(process(&mut ctx, &[ix], &[&seller]).await.is_err());
+}
+
+#[tokio::test]
+async fn buy_fails_with_wrong_seller() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+    create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+
+    // payment redirected to the buyer themselves
+    let mut ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, 1, None);
+    ix.accounts[3].pubkey = buyer.pubkey();
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(
+        result,
+        anchor_err(anchor_lang::error::ErrorCode::ConstraintHasOne),
+    );
+}
+
+#[tokio::test]
+async fn buy_fails_with_wrong_ruleset() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), Some(rule_set)).await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1, 0, Some(rule_set));
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+    create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+
+    let ix = buy_ix(
+        &mint,
+        &seller.pubkey(),
+        &buyer.pubkey(),
+        0,
+        1,
+        Some(Pubkey::new_unique()),
+    );
+    assert!(process(&mut ctx, &[ix], &[&buyer]).await.is_err());
+}
+
+#[tokio::test]
+async fn buy_fails_after_price_change() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+
+    let ix = list_ix(&mint, &seller.pubkey(), 1_000, 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+    create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+
+    // seller reprices before the buyer's tx lands
+    let ix = update_price_ix(&mint, &seller.pubkey(), 0, 5_000);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    let ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, 1_000, None);
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(result, program_err(ErrorCode::PriceMismatch));
+
+    let ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, 5_000, None);
+    process(&mut ctx, &[ix], &[&buyer]).await.unwrap();
+}
+
+// ----------------------------------------------------------------------------- legacy listings
+
+#[tokio::test]
+async fn unwinds_legacy_listing() {
+    let upgrade_authority = Keypair::new();
+    let mut ctx = setup_upgradeable(&upgrade_authority.pubkey()).await;
+    let authority = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let ix = system_instruction::transfer(&authority.pubkey(), &upgrade_authority.pubkey(), 100_000_000);
+    process(&mut ctx, &[ix], &[&authority]).await.unwrap();
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = create_legacy_listing(&mut ctx, &seller).await;
+
+    let legacy_listing = pda::find_legacy_listing(&mint).0;
+    let listing_item_token = get_associated_token_address(&legacy_listing, &mint);
+    let seller_item_token = get_associated_token_address(&seller.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(0));
+    let seller_before = lamports(&mut ctx, &seller.pubkey()).await;
+
+    let ix = unwind_legacy_listing_ix(
+        &legacy_listing,
+        &mint,
+        &seller.pubkey(),
+        &upgrade_authority.pubkey(),
+    );
+    process(&mut ctx, &[ix], &[&upgrade_authority]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(1));
+    assert!(ctx.banks_client.get_account(legacy_listing).await.unwrap().is_none());
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, None);
+    // the listing's and escrow's rent
+    let listing_rent = Rent::default().minimum_balance(LEGACY_LISTING_SIZE);
+    assert!(lamports(&mut ctx, &seller.pubkey()).await > seller_before + listing_rent);
+}
+
+#[tokio::test]
+async fn unwind_legacy_listing_fails_for_non_authority() {
+    let upgrade_authority = Keypair::new();
+    let mut ctx = setup_upgradeable(&upgrade_authority.pubkey()).await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = create_legacy_listing(&mut ctx, &seller).await;
+
+    // the seller can't pull their own item out, only the upgrade authority attests to who that is
+    let legacy_listing = pda::find_legacy_listing(&mint).0;
+    let ix = unwind_legacy_listing_ix(&legacy_listing, &mint, &seller.pubkey(), &seller.pubkey());
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(
+        result,
+        anchor_err(anchor_lang::error::ErrorCode::ConstraintRaw),
+    );
+
+    let listing_item_token = get_associated_token_address(&legacy_listing, &mint);
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, Some(1));
+}
+
+#[tokio::test]
+async fn unwinds_legacy_seller_listing() {
+    let upgrade_authority = Keypair::new();
+    let mut ctx = setup_upgradeable(&upgrade_authority.pubkey()).await;
+    let authority = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let ix = system_instruction::transfer(&authority.pubkey(), &upgrade_authority.pubkey(), 100_000_000);
+    process(&mut ctx, &[ix], &[&authority]).await.unwrap();
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = create_legacy_seller_listing(&mut ctx, &seller, 42_000).await;
+
+    let legacy_listing = pda::find_legacy_seller_listing(&mint, &seller.pubkey()).0;
+    let listing_item_token = get_associated_token_address(&legacy_listing, &mint);
+    let seller_item_token = get_associated_token_address(&seller.pubkey(), &mint);
+
+    // these record their seller, so nobody else can be attested
+    let ix = unwind_legacy_listing_ix(
+        &legacy_listing,
+        &mint,
+        &authority.pubkey(),
+        &upgrade_authority.pubkey(),
+    );
+    let result = process(&mut ctx, &[ix], &[&upgrade_authority]).await;
+    assert_custom_err(result, program_err(ErrorCode::SellerMismatch));
+
+    let ix = unwind_legacy_listing_ix(
+        &legacy_listing,
+        &mint,
+        &seller.pubkey(),
+        &upgrade_authority.pubkey(),
+    );
+    process(&mut ctx, &[ix], &[&upgrade_authority]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(1));
+    assert!(ctx.banks_client.get_account(legacy_listing).await.unwrap().is_none());
+    assert_eq!(token_balance(&mut ctx, &listing_item_token).await, None);
+}
+
+#[tokio::test]
+async fn migrates_pre_version_listing() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let payer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let mint = mint_pnft_in_collection(&mut ctx, &seller.pubkey(), None, &collection).await;
+    let (listing, account) = pre_version_listing(&mint, &seller.pubkey(), 3, 42_000, 1);
+    let old_rent = account.lamports;
+    ctx.set_account(&listing, &account.into());
+    let payer_before = lamports(&mut ctx, &payer.pubkey()).await;
+
+    let ix = migrate_listing_ix(&listing, &mint, &payer.pubkey());
+    process(&mut ctx, &[ix], &[&payer]).await.unwrap();
+
+    let account = ctx.banks_client.get_account(listing).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), Listing::SIZE);
+    let rent_delta = Rent::default().minimum_balance(Listing::SIZE) - old_rent;
+    assert_eq!(account.lamports, old_rent + rent_delta);
+    assert_eq!(lamports(&mut ctx, &payer.pubkey()).await, payer_before - rent_delta);
+
+    let state = fetch_listing(&mut ctx, &listing).await.unwrap();
+    assert_eq!(state.bump, pda::find_listing(&mint, &seller.pubkey(), 3).1);
+    assert_eq!(state.item, mint);
+    assert_eq!(state.item_token, get_associated_token_address(&listing, &mint));
+    assert_eq!(state.seller, seller.pubkey());
+    assert_eq!(state.collection, collection);
+    assert_eq!(state.nonce, 3);
+    assert_eq!(state.price, 42_000);
+    assert_eq!(state.version, pnft_transfer::LISTING_VERSION);
+    assert_eq!(state.reserved, [0; pnft_transfer::LISTING_RESERVED_SIZE]);
+
+    // another payer, or the retry would be the same txn
+    let ix = migrate_listing_ix(&listing, &mint, &seller.pubkey());
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::ListingAlreadyMigrated));
+}
+
+#[tokio::test]
+async fn migrates_pre_collection_listing() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let payer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let mint = mint_pnft_in_collection(&mut ctx, &seller.pubkey(), None, &collection).await;
+    // a lot of 3 at 42_000 each
+    let (listing, account) = pre_collection_listing(&mint, &seller.pubkey(), 3, 42_000, 3);
+    let old_rent = account.lamports;
+    ctx.set_account(&listing, &account.into());
+    let payer_before = lamports(&mut ctx, &payer.pubkey()).await;
+
+    let ix = migrate_listing_ix(&listing, &mint, &payer.pubkey());
+    process(&mut ctx, &[ix], &[&payer]).await.unwrap();
+
+    let account = ctx.banks_client.get_account(listing).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), Listing::SIZE);
+    let rent_delta = Rent::default().minimum_balance(Listing::SIZE) - old_rent;
+    assert_eq!(account.lamports, old_rent + rent_delta);
+    assert_eq!(lamports(&mut ctx, &payer.pubkey()).await, payer_before - rent_delta);
+
+    // nonce and price moved down past the inserted collection, the price now for the whole lot
+    let state = fetch_listing(&mut ctx, &listing).await.unwrap();
+    assert_eq!(state.bump, pda::find_listing(&mint, &seller.pubkey(), 3).1);
+    assert_eq!(state.item, mint);
+    assert_eq!(state.item_token, get_associated_token_address(&listing, &mint));
+    assert_eq!(state.seller, seller.pubkey());
+    assert_eq!(state.collection, collection);
+    assert_eq!(state.nonce, 3);
+    assert_eq!(state.price, 126_000);
+    assert_eq!(state.version, pnft_transfer::LISTING_VERSION);
+    assert_eq!(state.reserved, [0; pnft_transfer::LISTING_RESERVED_SIZE]);
+
+    // another payer, or the retry would be the same txn
+    let ix = migrate_listing_ix(&listing, &mint, &seller.pubkey());
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::ListingAlreadyMigrated));
+}
+
+#[tokio::test]
+async fn migrates_v1_listing() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let payer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let price = 42_000;
+
+    // a live listing, rewritten as version 1 still had it
+    let ix = list_ix(&mint, &seller.pubkey(), price, 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+    let (listing, account) = v1_listing(&mint, &seller.pubkey(), 0, price, 1);
+    let old_rent = account.lamports;
+    ctx.set_account(&listing, &account.into());
+    create_ata(&mut ctx, &buyer.pubkey(), &mint).await;
+
+    let ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, price, None);
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(result, program_err(ErrorCode::ListingNotMigrated));
+
+    let payer_before = lamports(&mut ctx, &payer.pubkey()).await;
+    let ix = migrate_listing_ix(&listing, &mint, &payer.pubkey());
+    process(&mut ctx, &[ix], &[&payer]).await.unwrap();
+
+    // shrinks, keeps its lamports and the payer pays nothing
+    let account = ctx.banks_client.get_account(listing).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), Listing::SIZE);
+    assert_eq!(account.lamports, old_rent);
+    assert_eq!(lamports(&mut ctx, &payer.pubkey()).await, payer_before);
+    let state = fetch_listing(&mut ctx, &listing).await.unwrap();
+    assert_eq!(state.price, price);
+    assert_eq!(state.version, pnft_transfer::LISTING_VERSION);
+
+    let listing_item_token = get_associated_token_address(&listing, &mint);
+    let escrow_rent = lamports(&mut ctx, &listing_item_token).await;
+    let seller_before = lamports(&mut ctx, &seller.pubkey()).await;
+    let ix = buy_ix(&mint, &seller.pubkey(), &buyer.pubkey(), 0, price, None);
+    process(&mut ctx, &[ix], &[&buyer]).await.unwrap();
+
+    // the surplus goes back to the seller with the rest
+    assert_eq!(
+        lamports(&mut ctx, &seller.pubkey()).await,
+        seller_before + price + old_rent + escrow_rent
+    );
+}
+
+#[tokio::test]
+async fn migrate_listing_rejects_legacy_layout() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = create_legacy_listing(&mut ctx, &seller).await;
+
+    let ix = migrate_listing_ix(&pda::find_legacy_listing(&mint).0, &mint, &seller.pubkey());
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::LegacyListingLayout));
+
+    // same size as the pre-collection layout, but seeded without a nonce
+    let mint = create_legacy_seller_listing(&mut ctx, &seller, 42_000).await;
+    let legacy_listing = pda::find_legacy_seller_listing(&mint, &seller.pubkey()).0;
+    let ix = migrate_listing_ix(&legacy_listing, &mint, &seller.pubkey());
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::LegacyListingLayout));
+}
+
+// ----------------------------------------------------------------------------- staking
+
+#[tokio::test]
+async fn stakes_and_unstakes_in_place() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let src = get_associated_token_address(&owner.pubkey(), &mint);
+    create_ata(&mut ctx, &receiver.pubkey(), &mint).await;
+
+    let ix = stake_ix(&mint, &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let stake_record = pda::find_stake_record(&mint, &owner.pubkey()).0;
+    let state = fetch_stake_record(&mut ctx, &stake_record).await.unwrap();
+    assert_eq!(state.item, mint);
+    assert_eq!(state.item_token, src);
+    assert_eq!(state.owner, owner.pubkey());
+    // never leaves the wallet
+    assert_eq!(token_balance(&mut ctx, &src).await, Some(1));
+
+    // locked, can't be moved or listed while staked
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), None);
+    assert!(process(&mut ctx, &[ix], &[&owner]).await.is_err());
+    let ix = list_ix(&mint, &owner.pubkey(), 1, 0, None);
+    assert!(process(&mut ctx, &[ix], &[&owner]).await.is_err());
+    assert_eq!(token_balance(&mut ctx, &src).await, Some(1));
+
+    let ix = unstake_ix(&mint, &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    assert!(fetch_stake_record(&mut ctx, &stake_record).await.is_none());
+
+    let ix = transfer_ix(&mint, &owner.pubkey(), &receiver.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &src).await, Some(0));
+}
+
+#[tokio::test]
+async fn unstake_fails_for_non_owner() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let thief = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+
+    let ix = stake_ix(&mint, &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    // thief points at the owner's stake record
+    let mut ix = unstake_ix(&mint, &thief.pubkey(), None);
+    let stake_record = pda::find_stake_record(&mint, &owner.pubkey()).0;
+    ix.accounts[2].pubkey = stake_record;
+    assert!(process(&mut ctx, &[ix], &[&thief]).await.is_err());
+    assert!(fetch_stake_record(&mut ctx, &stake_record).await.is_some());
+}
+
+// ----------------------------------------------------------------------------- rewards
+
+fn base_multiplier(collection: &Pubkey) -> Vec<CollectionMultiplier> {
+    vec![CollectionMultiplier {
+        collection: *collection,
+        multiplier_bps: pnft_transfer::BASE_MULTIPLIER_BPS,
+    }]
+}
+
+// pool rewards the staked item's collection at 1x
+async fn staked_with_pool(
+    ctx: &mut ProgramTestContext,
+    rate_per_second: u64,
+) -> (Keypair, Keypair, Pubkey, Pubkey) {
+    let pool_authority = funded_wallet(ctx, 1_000_000_000).await;
+    let owner = funded_wallet(ctx, 1_000_000_000).await;
+    let collection = create_collection(ctx).await;
+    let mint = mint_pnft_in_collection(ctx, &owner.pubkey(), None, &collection).await;
+    let reward_mint = create_mint(ctx, &pool_authority.pubkey()).await;
+
+    let multipliers = base_multiplier(&collection);
+    let ix = init_reward_pool_ix(&pool_authority.pubkey(), &reward_mint, rate_per_second, multipliers);
+    process(ctx, &[ix], &[&pool_authority]).await.unwrap();
+    let ix = stake_ix(&mint, &owner.pubkey(), None);
+    process(ctx, &[ix], &[&owner]).await.unwrap();
+
+    (pool_authority, owner, mint, reward_mint)
+}
+
+#[tokio::test]
+async fn claims_rewards_from_vault() {
+    let mut ctx = setup().await;
+    let (pool_authority, owner, mint, reward_mint) = staked_with_pool(&mut ctx, 10).await;
+
+    let reward_pool = pda::find_reward_pool(&pool_authority.pubkey(), &reward_mint).0;
+    let vault = get_associated_token_address(&reward_pool, &reward_mint);
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::ID,
+        &reward_mint,
+        &vault,
+        &pool_authority.pubkey(),
+        &[],
+        1_000_000,
+    )
+    .unwrap();
+    process(&mut ctx, &[ix], &[&pool_authority]).await.unwrap();
+
+    warp_seconds(&mut ctx, 100).await;
+    let ix = claim_rewards_ix(&mint, &owner.pubkey(), &pool_authority.pubkey(), &reward_mint);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let owner_reward_token = get_associated_token_address(&owner.pubkey(), &reward_mint);
+    let claimed = token_balance(&mut ctx, &owner_reward_token).await.unwrap();
+    assert!(claimed >= 1_000);
+    assert_eq!(token_balance(&mut ctx, &vault).await, Some(1_000_000 - claimed));
+
+    let stake_record = pda::find_stake_record(&mint, &owner.pubkey()).0;
+    let state = fetch_stake_record(&mut ctx, &stake_record).await.unwrap();
+    assert_eq!(state.reward_pool, reward_pool);
+    assert_eq!(
+        claimed,
+        10 * (state.claimed_until - state.staked_at) as u64
+    );
+}
+
+#[tokio::test]
+async fn claims_rewards_minted_by_the_pool() {
+    let mut ctx = setup().await;
+    let (pool_authority, owner, mint, reward_mint) = staked_with_pool(&mut ctx, 10).await;
+
+    let reward_pool = pda::find_reward_pool(&pool_authority.pubkey(), &reward_mint).0;
+    let ix = spl_token::instruction::set_authority(
+        &spl_token::ID,
+        &reward_mint,
+        Some(&reward_pool),
+        spl_token::instruction::AuthorityType::MintTokens,
+        &pool_authority.pubkey(),
+        &[],
+    )
+    .unwrap();
+    process(&mut ctx, &[ix], &[&pool_authority]).await.unwrap();
+
+    // empty vault, so this only works by minting
+    warp_seconds(&mut ctx, 100).await;
+    let ix = claim_rewards_ix(&mint, &owner.pubkey(), &pool_authority.pubkey(), &reward_mint);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let owner_reward_token = get_associated_token_address(&owner.pubkey(), &reward_mint);
+    assert!(token_balance(&mut ctx, &owner_reward_token).await.unwrap() >= 1_000);
+}
+
+#[tokio::test]
+async fn claim_fails_from_another_pool() {
+    let mut ctx = setup().await;
+    let (pool_authority, owner, mint, reward_mint) = staked_with_pool(&mut ctx, 0).await;
+    let ix = claim_rewards_ix(&mint, &owner.pubkey(), &pool_authority.pubkey(), &reward_mint);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    // second pool, same reward mint
+    let other_authority = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let ix = init_reward_pool_ix(&other_authority.pubkey(), &reward_mint, 10, vec![]);
+    process(&mut ctx, &[ix], &[&other_authority]).await.unwrap();
+
+    let ix = claim_rewards_ix(&mint, &owner.pubkey(), &other_authority.pubkey(), &reward_mint);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::WrongRewardPool));
+}
+
+#[tokio::test]
+async fn claim_fails_outside_pool_collections() {
+    let mut ctx = setup().await;
+    let (pool_authority, _, _, reward_mint) = staked_with_pool(&mut ctx, 10).await;
+    let reward_pool = pda::find_reward_pool(&pool_authority.pubkey(), &reward_mint).0;
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::ID,
+        &reward_mint,
+        &get_associated_token_address(&reward_pool, &reward_mint),
+        &pool_authority.pubkey(),
+        &[],
+        1_000_000,
+    )
+    .unwrap();
+    process(&mut ctx, &[ix], &[&pool_authority]).await.unwrap();
+
+    // a throwaway collection the pool never listed, and no collection at all
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let other_collection = create_collection(&mut ctx).await;
+    let unlisted = mint_pnft_in_collection(&mut ctx, &owner.pubkey(), None, &other_collection).await;
+    let unverified = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    for mint in [unlisted, unverified] {
+        let ix = stake_ix(&mint, &owner.pubkey(), None);
+        process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    }
+    warp_seconds(&mut ctx, 100).await;
+
+    for mint in [unlisted, unverified] {
+        let ix = claim_rewards_ix(&mint, &owner.pubkey(), &pool_authority.pubkey(), &reward_mint);
+        let result = process(&mut ctx, &[ix], &[&owner]).await;
+        assert_custom_err(result, program_err(ErrorCode::CollectionNotRewarded));
+    }
+    let owner_reward_token = get_associated_token_address(&owner.pubkey(), &reward_mint);
+    assert_eq!(token_balance(&mut ctx, &owner_reward_token).await, None);
+}
+
+#[tokio::test]
+async fn init_reward_pool_fails_for_unverified_collection() {
+    let mut ctx = setup().await;
+    let pool_authority = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let reward_mint = create_mint(&mut ctx, &pool_authority.pubkey()).await;
+
+    let multipliers = base_multiplier(&Pubkey::default());
+    let ix = init_reward_pool_ix(&pool_authority.pubkey(), &reward_mint, 10, multipliers);
+    let result = process(&mut ctx, &[ix], &[&pool_authority]).await;
+    assert_custom_err(result, program_err(ErrorCode::InvalidMultiplier));
+}
+
+// ----------------------------------------------------------------------------- lending
+
+const PRINCIPAL: u64 = 500_000_000;
+const LOAN_DURATION: i64 = 7 * 24 * 60 * 60;
+
+// lender offers PRINCIPAL at 10% apr for a week, borrower takes it with an item from the collection
+async fn taken_loan(ctx: &mut ProgramTestContext) -> (Keypair, Keypair, Pubkey) {
+    let lender = funded_wallet(ctx, 2_000_000_000).await;
+    let borrower = funded_wallet(ctx, 1_000_000_000).await;
+    let collection = create_collection(ctx).await;
+    let mint = mint_pnft_in_collection(ctx, &borrower.pubkey(), None, &collection).await;
+
+    let ix = offer_loan_ix(&lender.pubkey(), 0, &collection, PRINCIPAL, 1_000, LOAN_DURATION);
+    process(ctx, &[ix], &[&lender]).await.unwrap();
+
+    let borrower_before = lamports(ctx, &borrower.pubkey()).await;
+    let ix = take_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), &collection, None);
+    process(ctx, &[ix], &[&borrower]).await.unwrap();
+
+    let loan = pda::find_loan(&lender.pubkey(), 0).0;
+    let loan_item_token = get_associated_token_address(&loan, &mint);
+    assert_eq!(token_balance(ctx, &loan_item_token).await, Some(1));
+    // principal less the escrow's rent and fees
+    assert!(lamports(ctx, &borrower.pubkey()).await > borrower_before + PRINCIPAL / 2);
+    let state = fetch_loan(ctx, &loan).await.unwrap();
+    assert_eq!(state.borrower, borrower.pubkey());
+    assert_eq!(state.item, mint);
+
+    (lender, borrower, mint)
+}
+
+#[tokio::test]
+async fn repays_loan() {
+    let mut ctx = setup().await;
+    let (lender, borrower, mint) = taken_loan(&mut ctx).await;
+    let lender_before = lamports(&mut ctx, &lender.pubkey()).await;
+
+    warp_seconds(&mut ctx, LOAN_DURATION / 2).await;
+    let ix = repay_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), None);
+    process(&mut ctx, &[ix], &[&borrower]).await.unwrap();
+
+    let borrower_item_token = get_associated_token_address(&borrower.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &borrower_item_token).await, Some(1));
+    // principal + interest + the loan's rent
+    assert!(lamports(&mut ctx, &lender.pubkey()).await > lender_before + PRINCIPAL);
+    let loan = pda::find_loan(&lender.pubkey(), 0).0;
+    assert!(fetch_loan(&mut ctx, &loan).await.is_none());
+}
+
+#[tokio::test]
+async fn forecloses_overdue_loan() {
+    let mut ctx = setup().await;
+    let (lender, borrower, mint) = taken_loan(&mut ctx).await;
+
+    let ix = foreclose_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), None);
+    let result = process(&mut ctx, &[ix], &[&lender]).await;
+    assert_custom_err(result, program_err(ErrorCode::LoanNotOverdue));
+
+    warp_seconds(&mut ctx, LOAN_DURATION + 1).await;
+    let ix = foreclose_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), None);
+    process(&mut ctx, &[ix], &[&lender]).await.unwrap();
+
+    let lender_item_token = get_associated_token_address(&lender.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &lender_item_token).await, Some(1));
+}
+
+#[tokio::test]
+async fn take_loan_fails_outside_collection() {
+    let mut ctx = setup().await;
+    let lender = funded_wallet(&mut ctx, 2_000_000_000).await;
+    let borrower = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let mint = mint_pnft(&mut ctx, &borrower.pubkey(), None).await;
+
+    let ix = offer_loan_ix(&lender.pubkey(), 0, &collection, PRINCIPAL, 1_000, LOAN_DURATION);
+    process(&mut ctx, &[ix], &[&lender]).await.unwrap();
+
+    let ix = take_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), &collection, None);
+    let result = process(&mut ctx, &[ix], &[&borrower]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionMismatch));
+}
+
+#[tokio::test]
+async fn take_loan_fails_for_printable_master_edition() {
+    let mut ctx = setup().await;
+    let lender = funded_wallet(&mut ctx, 2_000_000_000).await;
+    let borrower = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let mint = mint_asset_with_print_supply(
+        &mut ctx,
+        &borrower.pubkey(),
+        TokenStandard::ProgrammableNonFungible,
+        None,
+        Some(collection),
+        PrintSupply::Limited(5),
+    )
+    .await;
+
+    let ix = offer_loan_ix(&lender.pubkey(), 0, &collection, PRINCIPAL, 1_000, LOAN_DURATION);
+    process(&mut ctx, &[ix], &[&lender]).await.unwrap();
+
+    let ix = take_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), &collection, None);
+    let result = process(&mut ctx, &[ix], &[&borrower]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+}
+
+// ----------------------------------------------------------------------------- rentals
+
+const DAILY_PRICE: u64 = 10_000_000;
+const DAY: i64 = 24 * 60 * 60;
+
+// owner lists for up to a week, renter rents for 2 days
+async fn rented_pnft(ctx: &mut ProgramTestContext) -> (Keypair, Keypair, Pubkey) {
+    let owner = funded_wallet(ctx, 1_000_000_000).await;
+    let renter = funded_wallet(ctx, 1_000_000_000).await;
+    let mint = mint_pnft(ctx, &owner.pubkey(), None).await;
+
+    let ix = list_for_rent_ix(&mint, &owner.pubkey(), DAILY_PRICE, 7, None, None);
+    process(ctx, &[ix], &[&owner]).await.unwrap();
+
+    let owner_before = lamports(ctx, &owner.pubkey()).await;
+    let ix = rent_pnft_ix(&mint, &owner.pubkey(), &renter.pubkey(), 2, None, None);
+    process(ctx, &[ix], &[&renter]).await.unwrap();
+
+    let renter_item_token = get_associated_token_address(&renter.pubkey(), &mint);
+    assert_eq!(token_balance(ctx, &renter_item_token).await, Some(1));
+    // 2 days up front + the escrow's rent
+    assert!(lamports(ctx, &owner.pubkey()).await >= owner_before + 2 * DAILY_PRICE);
+    let rental = pda::find_rental(&mint, &owner.pubkey()).0;
+    let state = fetch_rental(ctx, &rental).await.unwrap();
+    assert_eq!(state.renter, renter.pubkey());
+
+    (owner, renter, mint)
+}
+
+#[tokio::test]
+async fn rents_and_returns_after_expiry() {
+    let mut ctx = setup().await;
+    let (owner, renter, mint) = rented_pnft(&mut ctx).await;
+    let cranker = funded_wallet(&mut ctx, 1_000_000_000).await;
+
+    // locked in the renter's wallet
+    let ix = transfer_ix(&mint, &renter.pubkey(), &cranker.pubkey(), None);
+    assert!(process(&mut ctx, &[ix], &[&renter]).await.is_err());
+
+    let ix = end_rental_ix(&mint, &owner.pubkey(), &renter.pubkey(), &cranker.pubkey(), None);
+    let result = process(&mut ctx, &[ix], &[&cranker]).await;
+    assert_custom_err(result, program_err(ErrorCode::RentalNotExpired));
+
+    warp_seconds(&mut ctx, 2 * DAY + 1).await;
+    let ix = end_rental_ix(&mint, &owner.pubkey(), &renter.pubkey(), &cranker.pubkey(), None);
+    process(&mut ctx, &[ix], &[&cranker]).await.unwrap();
+
+    let owner_item_token = get_associated_token_address(&owner.pubkey(), &mint);
+    let renter_item_token = get_associated_token_address(&renter.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &owner_item_token).await, Some(1));
+    assert_eq!(token_balance(&mut ctx, &renter_item_token).await, Some(0));
+    let rental = pda::find_rental(&mint, &owner.pubkey()).0;
+    assert!(fetch_rental(&mut ctx, &rental).await.is_none());
+}
+
+#[tokio::test]
+async fn rent_fails_past_max_days() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let renter = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+
+    let ix = list_for_rent_ix(&mint, &owner.pubkey(), DAILY_PRICE, 7, None, None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let ix = rent_pnft_ix(&mint, &owner.pubkey(), &renter.pubkey(), 8, None, None);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::InvalidRentalDays));
+
+    // still the owner's to take back
+    let ix = cancel_rental_ix(&mint, &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let owner_item_token = get_associated_token_address(&owner.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &owner_item_token).await, Some(1));
+}
+
+#[tokio::test]
+async fn list_for_rent_fails_for_printable_master_edition() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_asset_with_print_supply(
+        &mut ctx,
+        &owner.pubkey(),
+        TokenStandard::ProgrammableNonFungible,
+        None,
+        None,
+        PrintSupply::Limited(5),
+    )
+    .await;
+
+    let ix = list_for_rent_ix(&mint, &owner.pubkey(), DAILY_PRICE, 7, None, None);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+}
+
+// ----------------------------------------------------------------------------- bundles
+
+const BUNDLE_PRICE: u64 = 300_000_000;
+
+async fn listed_bundle(ctx: &mut ProgramTestContext) -> (Keypair, Vec<Pubkey>) {
+    let seller = funded_wallet(ctx, 1_000_000_000).await;
+    let items = vec![
+        mint_pnft(ctx, &seller.pubkey(), None).await,
+        mint_pnft(ctx, &seller.pubkey(), None).await,
+    ];
+
+    let ix = list_bundle_ix(&seller.pubkey(), 0, BUNDLE_PRICE, &items, None);
+    process(ctx, &[ix], &[&seller]).await.unwrap();
+
+    let bundle = pda::find_bundle(&seller.pubkey(), 0).0;
+    for item in &items {
+        let escrow = get_associated_token_address(&bundle, item);
+        assert_eq!(token_balance(ctx, &escrow).await, Some(1));
+    }
+    assert_eq!(fetch_bundle(ctx, &bundle).await.unwrap().items, items);
+
+    (seller, items)
+}
+
+#[tokio::test]
+async fn buys_whole_bundle() {
+    let mut ctx = setup().await;
+    let (seller, items) = listed_bundle(&mut ctx).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let seller_before = lamports(&mut ctx, &seller.pubkey()).await;
+
+    let ix = buy_bundle_ix(&seller.pubkey(), 0, &buyer.pubkey(), &items, None);
+    process(&mut ctx, &[ix], &[&buyer]).await.unwrap();
+
+    for item in &items {
+        let buyer_item_token = get_associated_token_address(&buyer.pubkey(), item);
+        assert_eq!(token_balance(&mut ctx, &buyer_item_token).await, Some(1));
+    }
+    // price + the escrows' and bundle's rent
+    assert!(lamports(&mut ctx, &seller.pubkey()).await > seller_before + BUNDLE_PRICE);
+    let bundle = pda::find_bundle(&seller.pubkey(), 0).0;
+    assert!(fetch_bundle(&mut ctx, &bundle).await.is_none());
+}
+
+#[tokio::test]
+async fn buy_bundle_fails_with_items_out_of_order() {
+    let mut ctx = setup().await;
+    let (seller, mut items) = listed_bundle(&mut ctx).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+
+    items.reverse();
+    let ix = buy_bundle_ix(&seller.pubkey(), 0, &buyer.pubkey(), &items, None);
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(result, program_err(ErrorCode::BundleItemMismatch));
+
+    // and all or nothing
+    let ix = buy_bundle_ix(&seller.pubkey(), 0, &buyer.pubkey(), &items[..1], None);
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(result, program_err(ErrorCode::InvalidBundleSize));
+}
+
+#[tokio::test]
+async fn list_bundle_fails_for_printable_master_edition() {
+    let mut ctx = setup().await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let genuine = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let printable = mint_asset_with_print_supply(
+        &mut ctx,
+        &seller.pubkey(),
+        TokenStandard::ProgrammableNonFungible,
+        None,
+        None,
+        PrintSupply::Limited(5),
+    )
+    .await;
+
+    let ix = list_bundle_ix(&seller.pubkey(), 0, BUNDLE_PRICE, &[genuine, printable], None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadEdition));
+    // all or nothing, the genuine one stays with the seller too
+    let seller_item_token = get_associated_token_address(&seller.pubkey(), &genuine);
+    assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(1));
+}
+
+#[tokio::test]
+async fn cancels_bundle() {
+    let mut ctx = setup().await;
+    let (seller, items) = listed_bundle(&mut ctx).await;
+
+    let ix = cancel_bundle_ix(&seller.pubkey(), 0, &items);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    for item in &items {
+        let seller_item_token = get_associated_token_address(&seller.pubkey(), item);
+        assert_eq!(token_balance(&mut ctx, &seller_item_token).await, Some(1));
+    }
+    let bundle = pda::find_bundle(&seller.pubkey(), 0).0;
+    assert!(fetch_bundle(&mut ctx, &bundle).await.is_none());
+}
+
+// ----------------------------------------------------------------------------- marketplace controls
+
+#[tokio::test]
+async fn pause_halts_listing_and_buying() {
+    let mut ctx = setup().await;
+    let admin = funded_wallet(&mut ctx, 1_000_000_000).await;
+    set_config(&mut ctx, &admin.pubkey()).await;
+    let seller = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let buyer = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let listed = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    let unlisted = mint_pnft(&mut ctx, &seller.pubkey(), None).await;
+    create_ata(&mut ctx, &buyer.pubkey(), &listed).await;
+
+    let ix = list_ix(&listed, &seller.pubkey(), 1_000, 0, None);
+    process(&mut ctx, &[ix], &[&seller]).await.unwrap();
+
+    // only the config's authority can pause
+    let ix = set_paused_ix(&seller.pubkey(), true);
+    assert!(process(&mut ctx, &[ix], &[&seller]).await.is_err());
+    let ix = set_paused_ix(&admin.pubkey(), true);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    let ix = list_ix(&unlisted, &seller.pubkey(), 1_000, 0, None);
+    let result = process(&mut ctx, &[ix], &[&seller]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+    let ix = buy_ix(&listed, &seller.pubkey(), &buyer.pubkey(), 0, 1_000, None);
+    let result = process(&mut ctx, &[ix], &[&buyer]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+
+    let ix = set_paused_ix(&admin.pubkey(), false);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+    let ix = buy_ix(&listed, &seller.pubkey(), &buyer.pubkey(), 0, 1_000, None);
+    process(&mut ctx, &[ix], &[&buyer]).await.unwrap();
+}
+
+#[tokio::test]
+async fn frozen_collection_blocks_loans() {
+    let mut ctx = setup().await;
+    let admin = funded_wallet(&mut ctx, 1_000_000_000).await;
+    set_config(&mut ctx, &admin.pubkey()).await;
+    let lender = funded_wallet(&mut ctx, 2_000_000_000).await;
+    let borrower = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let mint = mint_pnft_in_collection(&mut ctx, &borrower.pubkey(), None, &collection).await;
+
+    let ix = offer_loan_ix(&lender.pubkey(), 0, &collection, PRINCIPAL, 1_000, LOAN_DURATION);
+    process(&mut ctx, &[ix], &[&lender]).await.unwrap();
+
+    let ix = set_collection_status_ix(&admin.pubkey(), &collection, CollectionState::Frozen);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    let ix = offer_loan_ix(&lender.pubkey(), 1, &collection, PRINCIPAL, 1_000, LOAN_DURATION);
+    let result = process(&mut ctx, &[ix], &[&lender]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+    let ix = take_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), &collection, None);
+    let result = process(&mut ctx, &[ix], &[&borrower]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+
+    let ix = set_collection_status_ix(&admin.pubkey(), &collection, CollectionState::Allowed);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+    let ix = take_loan_ix(&mint, &lender.pubkey(), 0, &borrower.pubkey(), &collection, None);
+    process(&mut ctx, &[ix], &[&borrower]).await.unwrap();
+}
+
+#[tokio::test]
+async fn pause_halts_rentals_and_bundles() {
+    let mut ctx = setup().await;
+    let admin = funded_wallet(&mut ctx, 1_000_000_000).await;
+    set_config(&mut ctx, &admin.pubkey()).await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let renter = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let rental = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let bundled = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+    let unlisted = mint_pnft(&mut ctx, &owner.pubkey(), None).await;
+
+    let ix = list_for_rent_ix(&rental, &owner.pubkey(), DAILY_PRICE, 7, None, None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let ix = list_bundle_ix(&owner.pubkey(), 0, BUNDLE_PRICE, &[bundled], None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let ix = set_paused_ix(&admin.pubkey(), true);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    let ix = list_for_rent_ix(&unlisted, &owner.pubkey(), DAILY_PRICE, 7, None, None);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+    let ix = rent_pnft_ix(&rental, &owner.pubkey(), &renter.pubkey(), 2, None, None);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+    let ix = list_bundle_ix(&owner.pubkey(), 1, BUNDLE_PRICE, &[unlisted], None);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+    let ix = buy_bundle_ix(&owner.pubkey(), 0, &renter.pubkey(), &[bundled], None);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::MarketPaused));
+
+    let ix = set_paused_ix(&admin.pubkey(), false);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+    let ix = rent_pnft_ix(&rental, &owner.pubkey(), &renter.pubkey(), 2, None, None);
+    process(&mut ctx, &[ix], &[&renter]).await.unwrap();
+    let ix = buy_bundle_ix(&owner.pubkey(), 0, &renter.pubkey(), &[bundled], None);
+    process(&mut ctx, &[ix], &[&renter]).await.unwrap();
+}
+
+#[tokio::test]
+async fn frozen_collection_blocks_rentals_and_bundles() {
+    let mut ctx = setup().await;
+    let admin = funded_wallet(&mut ctx, 1_000_000_000).await;
+    set_config(&mut ctx, &admin.pubkey()).await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let renter = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let collection = create_collection(&mut ctx).await;
+    let rental = mint_pnft_in_collection(&mut ctx, &owner.pubkey(), None, &collection).await;
+    let bundled = mint_pnft_in_collection(&mut ctx, &owner.pubkey(), None, &collection).await;
+    let unlisted = mint_pnft_in_collection(&mut ctx, &owner.pubkey(), None, &collection).await;
+    let c = Some(collection);
+
+    let ix = list_for_rent_ix(&rental, &owner.pubkey(), DAILY_PRICE, 7, c, None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let ix = list_bundle_ix(&owner.pubkey(), 0, BUNDLE_PRICE, &[bundled], c);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let ix = set_collection_status_ix(&admin.pubkey(), &collection, CollectionState::Frozen);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    let ix = list_for_rent_ix(&unlisted, &owner.pubkey(), DAILY_PRICE, 7, c, None);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+    let ix = rent_pnft_ix(&rental, &owner.pubkey(), &renter.pubkey(), 2, c, None);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+    let ix = list_bundle_ix(&owner.pubkey(), 1, BUNDLE_PRICE, &[unlisted], c);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+    let ix = buy_bundle_ix(&owner.pubkey(), 0, &renter.pubkey(), &[bundled], c);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::CollectionFrozen));
+
+    // passing the unverified status instead doesn't get around it
+    let ix = buy_bundle_ix(&owner.pubkey(), 0, &renter.pubkey(), &[bundled], None);
+    let result = process(&mut ctx, &[ix], &[&renter]).await;
+    assert_custom_err(result, program_err(ErrorCode::BadCollectionStatus));
+
+    // sellers can still pull out
+    let ix = cancel_bundle_ix(&owner.pubkey(), 0, &[bundled]);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let ix = cancel_rental_ix(&rental, &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+}
+
+// ----------------------------------------------------------------------------- vaults
+
+#[tokio::test]
+async fn deposits_and_withdraws_from_vault() {
+    let mut ctx = setup().await;
+    let rule_set = create_rule_set(&mut ctx, Rule::Pass).await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let mint = mint_pnft(&mut ctx, &owner.pubkey(), Some(rule_set)).await;
+
+    let ix = init_vault_ix(&owner.pubkey(), vec![], 0);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let ix = deposit_ix(&mint, &owner.pubkey(), &owner.pubkey(), Some(rule_set));
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let vault = pda::find_vault(&owner.pubkey()).0;
+    let vault_item_token = get_associated_token_address(&vault, &mint);
+    assert_eq!(token_balance(&mut ctx, &vault_item_token).await, Some(1));
+
+    let ix = withdraw_ix(&mint, &owner.pubkey(), &owner.pubkey(), &[], Some(rule_set));
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+
+    let owner_item_token = get_associated_token_address(&owner.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &owner_item_token).await, Some(1));
+    assert_eq!(token_balance(&mut ctx, &vault_item_token).await, None);
+}
+
+#[tokio::test]
+async fn vault_withdraw_to_needs_approvals() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let depositor = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let receiver = Keypair::new();
+    let (a, b) = (Keypair::new(), Keypair::new());
+    let mint = mint_pnft(&mut ctx, &depositor.pubkey(), None).await;
+
+    let ix = init_vault_ix(&owner.pubkey(), vec![a.pubkey(), b.pubkey()], 2);
+    process(&mut ctx, &[ix], &[&owner]).await.unwrap();
+    let vault = pda::find_vault(&owner.pubkey()).0;
+    assert_eq!(fetch_vault(&mut ctx, &vault).await.unwrap().threshold, 2);
+
+    // anyone can deposit into someone's vault
+    let ix = deposit_ix(&mint, &depositor.pubkey(), &owner.pubkey(), None);
+    process(&mut ctx, &[ix], &[&depositor]).await.unwrap();
+
+    // one approver short, and the same approver twice doesn't count double
+    let ix = withdraw_ix(&mint, &owner.pubkey(), &receiver.pubkey(), &[a.pubkey()], None);
+    let result = process(&mut ctx, &[ix], &[&owner, &a]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotEnoughApprovals));
+    let ix = withdraw_ix(&mint, &owner.pubkey(), &receiver.pubkey(), &[a.pubkey(), a.pubkey()], None);
+    let result = process(&mut ctx, &[ix], &[&owner, &a]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotEnoughApprovals));
+
+    let ix = withdraw_ix(&mint, &owner.pubkey(), &receiver.pubkey(), &[a.pubkey(), b.pubkey()], None);
+    process(&mut ctx, &[ix], &[&owner, &a, &b]).await.unwrap();
+
+    let receiver_item_token = get_associated_token_address(&receiver.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &receiver_item_token).await, Some(1));
+}
+
+#[tokio::test]
+async fn init_vault_fails_with_bad_threshold() {
+    let mut ctx = setup().await;
+    let owner = funded_wallet(&mut ctx, 1_000_000_000).await;
+    let approver = Pubkey::new_unique();
+
+    let ix = init_vault_ix(&owner.pubkey(), vec![approver], 2);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::InvalidThreshold));
+
+    let ix = init_vault_ix(&owner.pubkey(), vec![approver, approver], 2);
+    let result = process(&mut ctx, &[ix], &[&owner]).await;
+    assert_custom_err(result, program_err(ErrorCode::InvalidThreshold));
+}
+
+// ----------------------------------------------------------------------------- multisig
+
+const TIMELOCK: i64 = 60 * 60;
+
+// 2 of 3 multisig holding a pNFT in its vault, the signers are funded so they can pay
+async fn multisig_with_item(ctx: &mut ProgramTestContext) -> (Pubkey, Vec<Keypair>, Pubkey) {
+    let creator = funded_wallet(ctx, 1_000_000_000).await;
+    let mut signers = vec![];
+    for _ in 0..3 {
+        signers.push(funded_wallet(ctx, 1_000_000_000).await);
+    }
+    let keys = signers.iter().map(|s| s.pubkey()).collect();
+    let ix = create_multisig_ix(&creator.pubkey(), 0, keys, 2, TIMELOCK);
+    process(ctx, &[ix], &[&creator]).await.unwrap();
+
+    let multisig = pda::find_multisig(&creator.pubkey(), 0).0;
+    let mint = mint_pnft(ctx, &creator.pubkey(), None).await;
+    let ix = deposit_ix(&mint, &creator.pubkey(), &multisig, None);
+    process(ctx, &[ix], &[&creator]).await.unwrap();
+
+    (multisig, signers, mint)
+}
+
+#[tokio::test]
+async fn multisig_executes_withdrawal_after_approvals_and_timelock() {
+    let mut ctx = setup().await;
+    let (multisig, signers, mint) = multisig_with_item(&mut ctx).await;
+    let (a, b) = (&signers[0], &signers[1]);
+    let receiver = Keypair::new();
+
+    let ix = propose_withdrawal_ix(&multisig, 0, &a.pubkey(), &mint, &receiver.pubkey());
+    process(&mut ctx, &[ix], &[a]).await.unwrap();
+    assert_eq!(fetch_multisig(&mut ctx, &multisig).await.unwrap().proposal_count, 1);
+
+    // each approval counts once
+    let approve = approve_ix(&multisig, 0, &a.pubkey());
+    let result = process(&mut ctx, &[approve.clone(), approve], &[a]).await;
+    assert_custom_err(result, program_err(ErrorCode::AlreadyApproved));
+    let ix = approve_ix(&multisig, 0, &a.pubkey());
+    process(&mut ctx, &[ix], &[a]).await.unwrap();
+
+    let ix = execute_withdrawal_ix(&multisig, 0, &a.pubkey(), &mint, &receiver.pubkey(), &a.pubkey());
+    let result = process(&mut ctx, &[ix], &[a]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotEnoughApprovals));
+
+    let ix = approve_ix(&multisig, 0, &b.pubkey());
+    process(&mut ctx, &[ix], &[b]).await.unwrap();
+    let proposal = pda::find_proposal(&multisig, 0).0;
+    assert_ne!(fetch_proposal(&mut ctx, &proposal).await.unwrap().approved_at, 0);
+
+    // different payer so it isn't the same txn as above
+    let ix = execute_withdrawal_ix(&multisig, 0, &a.pubkey(), &mint, &receiver.pubkey(), &b.pubkey());
+    let result = process(&mut ctx, &[ix], &[b]).await;
+    assert_custom_err(result, program_err(ErrorCode::TimelockActive));
+
+    warp_seconds(&mut ctx, TIMELOCK).await;
+    let ix = execute_withdrawal_ix(&multisig, 0, &a.pubkey(), &mint, &receiver.pubkey(), &b.pubkey());
+    process(&mut ctx, &[ix], &[b]).await.unwrap();
+
+    let receiver_item_token = get_associated_token_address(&receiver.pubkey(), &mint);
+    assert_eq!(token_balance(&mut ctx, &receiver_item_token).await, Some(1));
+    assert!(fetch_proposal(&mut ctx, &proposal).await.is_none());
+}
+
+#[tokio::test]
+async fn multisig_rejects_outsiders() {
+    let mut ctx = setup().await;
+    let (multisig, signers, mint) = multisig_with_item(&mut ctx).await;
+    let outsider = funded_wallet(&mut ctx, 1_000_000_000).await;
+
+    let ix = propose_withdrawal_ix(&multisig, 0, &outsider.pubkey(), &mint, &outsider.pubkey());
+    let result = process(&mut ctx, &[ix], &[&outsider]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotASigner));
+
+    let a = &signers[0];
+    let ix = propose_withdrawal_ix(&multisig, 0, &a.pubkey(), &mint, &outsider.pubkey());
+    process(&mut ctx, &[ix], &[a]).await.unwrap();
+    let ix = approve_ix(&multisig, 0, &outsider.pubkey());
+    let result = process(&mut ctx, &[ix], &[&outsider]).await;
+    assert_custom_err(result, program_err(ErrorCode::NotASigner));
+}
@@ -6,13 +6,14 @@ import {
   TOKEN_PROGRAM_ID
 } from "@solana/spl-token";
 
-import { Keypair, Transaction } from "@solana/web3.js";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
 import { expect } from "chai";
 import {
   buildAndSendTx,
   createAndFundATA,
   createFundedWallet,
   createTokenAuthorizationRules,
+  fetchListingsBySeller,
   findListingPda
 } from "../utils/pnft";
 import { PnftTransfer } from "../target/types/pnft_transfer";
@@ -77,13 +78,17 @@ describe("pnft_transfer tests", () => {
       nftMint: mint,
       destAta: destAta.address,
       owner: nftOwner.publicKey,
-      receiver: nftReceiver.publicKey
+      receiver: nftReceiver.publicKey,
+      memo: 'order 42',
     })
-    await buildAndSendTx({
+    txid = await buildAndSendTx({
       provider,
       ixs: [await builder.instruction()],
       extraSigners: [nftOwner],
     });
+    // written through the memo program too, not just the event
+    const transferTx = await connection.getTransaction(txid, { commitment: 'confirmed' });
+    expect(transferTx.meta.logMessages.join('\n')).to.contain('order 42');
 
     console.log('what..');
 
@@ -111,12 +116,13 @@ describe("pnft_transfer tests", () => {
     });
 
 
-    let [listingPda] = findListingPda(mint, PROG.programId);
+    const nonce = new anchor.BN(0);
+    let [listingPda] = findListingPda(mint, creator.publicKey, nonce, PROG.programId);
     // listing's ata
     let listingItemToken = getAssociatedTokenAddressSync(mint, listingPda, true);
 
     let price = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL * 0.01);
-    let builder = await pNftTransferClient.buildListPNFT(price, {
+    let builder = await pNftTransferClient.buildListPNFT(price, nonce, {
       nftMint: mint,
       listing: listingPda,
       listingItemToken,
@@ -136,6 +142,12 @@ describe("pnft_transfer tests", () => {
     tokenBalance = await connection.getTokenAccountBalance(listingItemToken);
     expect(tokenBalance.value.uiAmount).to.equal(1);
 
+    const sellerListings = await fetchListingsBySeller(PROG, creator.publicKey);
+    expect(sellerListings.map((l) => l.publicKey.toBase58())).to.deep.equal([listingPda.toBase58()]);
+    // no verified collection, the client passed the default pubkey's collection status
+    const listing = await PROG.account.listing.fetch(listingPda);
+    expect(listing.collection.toBase58()).to.equal(PublicKey.default.toBase58());
+
     // now making a withdraw
     const buyerProvider = new AnchorProvider(connection, new NodeWallet(buyer), provider.opts);
 
@@ -148,11 +160,16 @@ describe("pnft_transfer tests", () => {
     txid = await buyerProvider.sendAndConfirm(tx);
     console.log(`created buyer's ata ${buyerItemToken}: ${txid}`);
 
-    builder = await pNftTransferClient.buildBuyPNFT( {
+    const sellerBalanceBefore = await connection.getBalance(creator.publicKey);
+
+    builder = await pNftTransferClient.buildBuyPNFT({
       nftMint: mint,
       listing: listingPda,
       listingItemToken,
       buyer: buyer.publicKey,
+      seller: creator.publicKey,
+      nonce,
+      expectedPrice: price,
     });
     txid = await buildAndSendTx({
       provider: buyerProvider,
@@ -162,6 +179,14 @@ describe("pnft_transfer tests", () => {
 
     console.log(` ------->>>>> withdrew txid: ${txid}`);
 
+    tokenBalance = await connection.getTokenAccountBalance(buyerItemToken);
+    expect(tokenBalance.value.uiAmount).to.equal(1);
+    const sellerBalanceAfter = await connection.getBalance(creator.publicKey);
+    // seller gets the price plus the listing's and escrow's rent back
+    expect(sellerBalanceAfter - sellerBalanceBefore).to.be.greaterThan(price.toNumber());
+    expect(await connection.getAccountInfo(listingPda)).to.be.null;
+    expect(await connection.getAccountInfo(listingItemToken)).to.be.null;
+
 
 
 
@@ -9,9 +9,11 @@ import * as anchor from "@project-serum/anchor";
 import { Idl } from "@project-serum/anchor";
 import { Connection, PublicKey, SystemProgram, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
 import { PnftTransfer } from "../target/types/pnft_transfer";
-import { fetchNft, findTokenRecordPDA } from "./pnft";
+import { fetchNft, findCollectionStatusPda, findConfigPda, findTokenRecordPDA } from "./pnft";
 import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
+export const MEMO_PROGRAM_ID = new PublicKey("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
 export class PNftTransferClient  {
 
     wallet: anchor.Wallet;
@@ -82,6 +84,10 @@ export class PNftTransferClient  {
             meta
         );
         const ruleSet = inflatedMeta.programmableConfig?.ruleSet;
+        // what the program checks the marketplace controls against
+        const collection = inflatedMeta.collection?.verified
+            ? inflatedMeta.collection.key
+            : PublicKey.default;
 
         const [ownerTokenRecordPda, ownerTokenRecordBump] =
             await findTokenRecordPDA(nftMint, sourceAta);
@@ -111,24 +117,31 @@ export class PNftTransferClient  {
             destTokenRecordBump,
             destTokenRecordPda,
             ruleSet,
+            collection,
             nftEditionPda,
             authDataSerialized,
         };
     }
 
 
+    // memo ends up in the Transferred event, and is also written through the SPL Memo program.
+    // payer (e.g. a relayer, it has to sign too) covers the dest token record instead of the owner
     async buildTransferPNFT({
         nftMint,
         sourceAta,
         destAta,
         owner,
-        receiver
+        receiver,
+        memo,
+        payer,
     }: {
         nftMint: PublicKey;
         sourceAta: PublicKey;
         destAta: PublicKey;
         owner: PublicKey;
         receiver: PublicKey;
+        memo?: string;
+        payer?: PublicKey;
     }) {
         //pnft
         const {
@@ -155,8 +168,25 @@ export class PNftTransferClient  {
             });
         }
 
+        // the program finds the memo program by key, so it just goes after the ruleset
+        if (memo) {
+            remainingAccounts.push({
+                pubkey: MEMO_PROGRAM_ID,
+                isSigner: false,
+                isWritable: false,
+            });
+        }
+        // the payer slot is last, after the ruleset and memo program
+        if (payer && !payer.equals(owner)) {
+            remainingAccounts.push({
+                pubkey: payer,
+                isSigner: true,
+                isWritable: true,
+            });
+        }
+
         const builder = this.program.methods
-            .transferPnft(authDataSerialized, !!ruleSet)
+            .transferPnft(authDataSerialized, !!ruleSet, memo ?? null)
             .accounts({
                 owner,
                 src: sourceAta,
@@ -183,7 +213,7 @@ export class PNftTransferClient  {
     }
 
 
-    async buildListPNFT(priceBN, {
+    async buildListPNFT(priceBN, nonceBN, {
         nftMint,
         listing,
         listingItemToken,
@@ -205,6 +235,7 @@ export class PNftTransferClient  {
             destTokenRecordBump,
             destTokenRecordPda,
             ruleSet,
+            collection,
             nftEditionPda,
             authDataSerialized,
         } = await this.prepPnftAccounts({
@@ -231,13 +262,15 @@ export class PNftTransferClient  {
 
 
         const builder = this.program.methods
-          .listPnft(priceBN, authDataSerialized, !!ruleSet)
+          .listPnft(priceBN, nonceBN, authDataSerialized, !!ruleSet)
           .accounts({
               item: nftMint,
               authorityItemToken,
               listing,
               listingItemToken,
               authority,
+              config: findConfigPda(this.program.programId)[0],
+              collectionStatus: findCollectionStatusPda(collection, this.program.programId)[0],
               tokenProgram: TOKEN_PROGRAM_ID,
               systemProgram: SystemProgram.programId,
               rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -255,14 +288,22 @@ export class PNftTransferClient  {
         return builder
     }
 
+    // expectedPrice, seller and nonce are what the buyer saw, the buy fails if the listing
+    // changed since
     async buildBuyPNFT({nftMint,
                         listing,
                         listingItemToken,
-                        buyer}: {
+                        buyer,
+                        seller,
+                        nonce,
+                        expectedPrice}: {
         nftMint: PublicKey;
         listingItemToken: PublicKey;
         buyer: PublicKey;
         listing: PublicKey;
+        seller: PublicKey;
+        nonce: anchor.BN;
+        expectedPrice: anchor.BN;
     }) {
 
         const buyerItemToken = getAssociatedTokenAddressSync(nftMint, buyer);
@@ -275,6 +316,7 @@ export class PNftTransferClient  {
             destTokenRecordBump,
             destTokenRecordPda,
             ruleSet,
+            collection,
             nftEditionPda,
             authDataSerialized,
         } = await this.prepPnftAccounts({
@@ -309,7 +351,7 @@ export class PNftTransferClient  {
         __kind: 'Seeds',
         fields: [
           {
-            seeds: [nftMint.toBuffer(), Buffer.from("listings")],
+            seeds: [nftMint.toBuffer(), seller.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8), Buffer.from("listings")],
           },
         ],
 
@@ -321,7 +363,7 @@ export class PNftTransferClient  {
           name: "SourceSeeds",
           payload: {
             name: "Seeds",
-            seeds: [nftMint.toBuffer(), Buffer.from("listings")]
+            seeds: [nftMint.toBuffer(), seller.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8), Buffer.from("listings")]
           },
         }]
       };
@@ -332,13 +374,16 @@ export class PNftTransferClient  {
 
       const builder = this.program.methods
           // .buyPnft(authDataSerialized, !!ruleSet)
-          .buyPnft()
+          .buyPnft(expectedPrice, seller, nonce)
           .accounts({
               listing,
+              seller,
               item: nftMint,
               listingItemToken,
               buyerItemToken,
               buyer,
+              config: findConfigPda(this.program.programId)[0],
+              collectionStatus: findCollectionStatusPda(collection, this.program.programId)[0],
               associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
               tokenProgram: TOKEN_PROGRAM_ID,
               systemProgram: SystemProgram.programId,
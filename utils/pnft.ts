@@ -610,13 +610,56 @@ export const LISTINGS_SPACE = 'listings';
 
 export const PROFILE = 'profile';
 
-export const findListingPda = (nftMint: PublicKey, progid: PublicKey): [PublicKey, number] => {
+export const findListingPda = (
+  nftMint: PublicKey,
+  seller: PublicKey,
+  nonce: anchor.BN,
+  progid: PublicKey
+): [PublicKey, number] => {
   return anchor.web3.PublicKey.findProgramAddressSync(
     [
       nftMint.toBuffer(),
+      seller.toBuffer(),
+      nonce.toArrayLike(Buffer, 'le', 8),
       Buffer.from(anchor.utils.bytes.utf8.encode(LISTINGS_SPACE)),
     ],
     progid,
   );
 }
 
+export const findConfigPda = (progid: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode('config'))],
+    progid,
+  );
+}
+
+// items without a verified collection go by the default pubkey's status
+export const findCollectionStatusPda = (
+  collection: PublicKey,
+  progid: PublicKey
+): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      collection.toBuffer(),
+      Buffer.from(anchor.utils.bytes.utf8.encode('collection_status')),
+    ],
+    progid,
+  );
+}
+
+// mirrors LISTING_*_OFFSET in the program, for memcmp filters
+export const LISTING_SELLER_OFFSET = 8 + 1 + 32 + 32;
+export const LISTING_COLLECTION_OFFSET = LISTING_SELLER_OFFSET + 32;
+
+export const fetchListingsBySeller = async (program: anchor.Program<any>, seller: PublicKey) => {
+  return program.account.listing.all([
+    { memcmp: { offset: LISTING_SELLER_OFFSET, bytes: seller.toBase58() } },
+  ]);
+}
+
+export const fetchListingsByCollection = async (program: anchor.Program<any>, collection: PublicKey) => {
+  return program.account.listing.all([
+    { memcmp: { offset: LISTING_COLLECTION_OFFSET, bytes: collection.toBase58() } },
+  ]);
+}